@@ -0,0 +1,59 @@
+//! Compares the mutex-backed channels against the lock-free backends.
+//!
+//! Run with `cargo bench`. Every case sends the same total number of messages, split across the
+//! producers, to a single consumer and prints the achieved throughput.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use chanus::{Reciever, Sender};
+
+const MESSAGES: usize = 1_000_000;
+const RUNS: usize = 5;
+
+fn run(make: fn() -> (Sender<usize>, Reciever<usize>), producers: usize) -> Duration {
+    let (tx, mut rx) = make();
+    let start = Instant::now();
+    let handles: Vec<_> = (0..producers)
+        .map(|_| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..MESSAGES / producers {
+                    tx.send(i).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut received = 0;
+    while rx.recv().is_ok() {
+        received += 1;
+    }
+    let elapsed = start.elapsed();
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert_eq!(received, MESSAGES / producers * producers);
+    elapsed
+}
+
+fn bench(name: &str, make: fn() -> (Sender<usize>, Reciever<usize>)) {
+    for producers in [1, 4, 32] {
+        // best of a few runs, the first one also pays for warming up the allocator.
+        let best = (0..RUNS).map(|_| run(make, producers)).min().unwrap();
+        let rate = MESSAGES as f64 / best.as_secs_f64() / 1e6;
+        println!("{name:<24} {producers:>3} producers  {best:>10.2?}  {rate:>7.2} Mmsg/s");
+    }
+}
+
+fn main() {
+    bench("mutex unbounded", chanus::unbounded);
+    bench("lock-free unbounded", chanus::lock_free::unbounded);
+    bench("mutex bounded(1024)", || chanus::bounded(1024));
+    bench("lock-free bounded(1024)", || {
+        chanus::lock_free::bounded(1024)
+    });
+}
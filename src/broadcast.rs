@@ -0,0 +1,338 @@
+//! Broadcast channels, where every subscriber recieves every message.
+//!
+//! The messages sit in a ring buffer of fixed capacity shared by all subscribers, each of which
+//! only keeps its position in it. Publishing never blocks: once the ring is full the oldest
+//! message gets overwritten, and a subscriber which hadn't read it yet finds out through
+//! [`RecvError::Lagged`] on its next `recv`.
+
+#[cfg(test)]
+use alloc::vec::Vec;
+use alloc::{collections::VecDeque, sync::Arc};
+use core::{error::Error, fmt};
+
+use crate::{
+    lock,
+    sync::{Condvar, Mutex},
+    SendErr,
+};
+
+struct Shared<T> {
+    mu: Mutex<Ring<T>>,
+    // subscribers waiting for the next message park here.
+    cond: Condvar,
+    cap: usize,
+}
+
+struct Ring<T> {
+    // never grows beyond `cap`, so it never reallocates after construction.
+    buf: VecDeque<T>,
+    // position of the oldest message still in `buf`, positions keep counting up across
+    // overwrites.
+    head: u64,
+    senders: usize,
+    receivers: usize,
+}
+
+impl<T> Ring<T> {
+    // position the next message will get.
+    fn tail(&self) -> u64 {
+        self.head + self.buf.len() as u64
+    }
+
+    // clones the message at position `next` for a subscriber and moves it on.
+    fn read(&self, next: &mut u64) -> Result<T, TryRecvError>
+    where
+        T: Clone,
+    {
+        if *next < self.head {
+            // skip ahead to the oldest message we still have.
+            let missed = self.head - *next;
+            *next = self.head;
+            return Err(TryRecvError::Lagged(missed));
+        }
+        if *next == self.tail() {
+            if self.senders == 0 {
+                return Err(TryRecvError::Disconnected);
+            }
+            return Err(TryRecvError::Empty);
+        }
+
+        let v = self.buf[(*next - self.head) as usize].clone();
+        *next += 1;
+        Ok(v)
+    }
+}
+
+/// Error returned by [`Reciever::recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// All senders are gone and every message was recieved.
+    Disconnected,
+    /// The subscriber fell behind and this many messages got overwritten before it read them.
+    /// The next `recv` continues with the oldest message still around.
+    Lagged(u64),
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disconnected => f.write_str("receiving on an empty and disconnected channel"),
+            Self::Lagged(n) => write!(f, "receiver lagged behind by {n} messages"),
+        }
+    }
+}
+
+impl Error for RecvError {}
+
+/// Error returned by [`Reciever::try_recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// No new message yet.
+    Empty,
+    /// All senders are gone and every message was recieved.
+    Disconnected,
+    /// See [`RecvError::Lagged`].
+    Lagged(u64),
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("receiving on an empty channel"),
+            Self::Disconnected => f.write_str("receiving on an empty and disconnected channel"),
+            Self::Lagged(n) => write!(f, "receiver lagged behind by {n} messages"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+impl From<RecvError> for TryRecvError {
+    fn from(err: RecvError) -> Self {
+        match err {
+            RecvError::Disconnected => Self::Disconnected,
+            RecvError::Lagged(n) => Self::Lagged(n),
+        }
+    }
+}
+
+/// The publishing half of a broadcast channel, can be cloned to publish from multiple threads.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Publishes a value to every current subscriber. Never blocks, on a full ring the oldest
+    /// message is dropped instead.
+    ///
+    /// Fails with [`SendErr`] holding the value if there are no subscribers.
+    pub fn send(&self, val: T) -> Result<(), SendErr<T>> {
+        let mut guard = lock(&self.shared.mu);
+        if guard.receivers == 0 {
+            return Err(SendErr(val));
+        }
+
+        // the overwritten message is only dropped once the ring is consistent again and
+        // unlocked, its `Drop` might panic.
+        let mut oldest = None;
+        if guard.buf.len() == self.shared.cap {
+            oldest = guard.buf.pop_front();
+            guard.head += 1;
+        }
+        guard.buf.push_back(val);
+        drop(guard);
+        self.shared.cond.notify_all();
+        drop(oldest);
+        Ok(())
+    }
+
+    /// Creates a new subscriber, which recieves the messages published from now on.
+    pub fn subscribe(&self) -> Reciever<T> {
+        let mut guard = lock(&self.shared.mu);
+        guard.receivers += 1;
+        Reciever {
+            shared: Arc::clone(&self.shared),
+            next: guard.tail(),
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        lock(&self.shared.mu).senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut guard = lock(&self.shared.mu);
+        guard.senders -= 1;
+        if guard.senders == 0 {
+            drop(guard);
+            self.shared.cond.notify_all();
+        }
+    }
+}
+
+/// A subscriber of a broadcast channel.
+pub struct Reciever<T> {
+    shared: Arc<Shared<T>>,
+    // position of the next message to recieve.
+    next: u64,
+}
+
+impl<T: Clone> Reciever<T> {
+    /// Blocks untill the next message is published and returns a clone of it.
+    ///
+    /// Fails with [`RecvError::Lagged`] if messages got overwritten before this subscriber read
+    /// them, and with [`RecvError::Disconnected`] once all senders are gone and every message was
+    /// recieved.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let mut guard = lock(&self.shared.mu);
+        loop {
+            match guard.read(&mut self.next) {
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => return Err(RecvError::Disconnected),
+                Err(TryRecvError::Lagged(n)) => return Err(RecvError::Lagged(n)),
+                Ok(v) => return Ok(v),
+            }
+            guard = crate::wait(&self.shared.cond, guard, None);
+        }
+    }
+
+    /// Attempts to recieve the next message without blocking.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let guard = lock(&self.shared.mu);
+        guard.read(&mut self.next)
+    }
+}
+
+impl<T> Clone for Reciever<T> {
+    /// Creates another subscriber at the same position, it recieves the same messages as this
+    /// one from here on.
+    fn clone(&self) -> Self {
+        lock(&self.shared.mu).receivers += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Drop for Reciever<T> {
+    fn drop(&mut self) {
+        lock(&self.shared.mu).receivers -= 1;
+    }
+}
+
+/// Creates a broadcast channel keeping the last `cap` messages around for slow subscribers.
+///
+/// More subscribers are created with [`Sender::subscribe`].
+///
+/// # Panics
+///
+/// If `cap` is zero.
+pub fn broadcast<T>(cap: usize) -> (Sender<T>, Reciever<T>) {
+    assert!(cap > 0, "capacity must be positive");
+
+    let shared = Arc::new(Shared {
+        mu: Mutex::new(Ring {
+            buf: VecDeque::with_capacity(cap),
+            head: 0,
+            senders: 1,
+            receivers: 1,
+        }),
+        cond: Condvar::new(),
+        cap,
+    });
+    (
+        Sender {
+            shared: Arc::clone(&shared),
+        },
+        Reciever { shared, next: 0 },
+    )
+}
+
+#[test]
+fn every_subscriber_gets_every_message() {
+    let (tx, rx1) = broadcast(16);
+    let rx2 = tx.subscribe();
+    let handles: Vec<_> = [rx1, rx2]
+        .into_iter()
+        .map(|mut rx| {
+            std::thread::spawn(move || {
+                let mut got = Vec::new();
+                while let Ok(v) = rx.recv() {
+                    got.push(v);
+                }
+                got
+            })
+        })
+        .collect();
+    for i in 0..10 {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+    for h in handles {
+        assert_eq!(h.join().unwrap(), (0..10).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn slow_subscriber_lags() {
+    let (tx, mut rx) = broadcast(2);
+    for i in 0..5 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(rx.recv(), Err(RecvError::Lagged(3)));
+    assert_eq!(rx.recv(), Ok(3));
+    assert_eq!(rx.try_recv(), Ok(4));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn subscribe_starts_at_tail() {
+    let (tx, mut rx1) = broadcast(4);
+    tx.send(1).unwrap();
+    let mut rx2 = tx.subscribe();
+    tx.send(2).unwrap();
+    assert_eq!(rx1.try_recv(), Ok(1));
+    assert_eq!(rx1.try_recv(), Ok(2));
+    assert_eq!(rx2.try_recv(), Ok(2));
+    drop(tx);
+    assert_eq!(rx2.recv(), Err(RecvError::Disconnected));
+}
+
+#[test]
+fn send_without_subscribers_fails() {
+    let (tx, rx) = broadcast(4);
+    drop(rx);
+    assert_eq!(tx.send(1), Err(SendErr(1)));
+    let mut rx = tx.subscribe();
+    tx.send(2).unwrap();
+    assert_eq!(rx.try_recv(), Ok(2));
+}
+
+#[test]
+fn panicking_drop_of_overwritten_message() {
+    #[derive(Clone)]
+    struct Bomb(i32);
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            if self.0 == 0 && !std::thread::panicking() {
+                panic!("dropped");
+            }
+        }
+    }
+
+    let (tx, mut rx) = broadcast(1);
+    let _ = tx.send(Bomb(0));
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tx.send(Bomb(1))));
+    assert!(res.is_err());
+    // the overwrite itself went through before the old message blew up.
+    assert!(matches!(rx.try_recv(), Err(TryRecvError::Lagged(1))));
+    assert!(matches!(rx.try_recv(), Ok(Bomb(1))));
+}
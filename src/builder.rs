@@ -0,0 +1,83 @@
+use crate::{
+    lock_free::{Array, List, Queue},
+    stats::{Latency, Stats},
+    Inner, Reciever, Sender,
+};
+
+/// Creates a channel with opt-in features, for everything the plain constructors like
+/// [`unbounded`](crate::unbounded) don't cover.
+///
+/// ```
+/// let (tx, mut rx) = chanus::Builder::new().bounded(16).stats().build();
+/// tx.send(1).unwrap();
+/// assert_eq!(rx.recv(), Ok(1));
+/// assert_eq!(tx.stats().unwrap().sent, 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    cap: Option<usize>,
+    lock_free: bool,
+    stats: bool,
+    latency: bool,
+}
+
+impl Builder {
+    /// An unbounded, mutex-backed channel without any extras, like [`unbounded`](crate::unbounded).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Holds at most `cap` messages, see [`bounded`](crate::bounded).
+    pub fn bounded(mut self, cap: usize) -> Self {
+        self.cap = Some(cap);
+        self
+    }
+
+    /// Keeps the messages in a lock-free queue, see [`lock_free`](crate::lock_free). Has no
+    /// effect on a rendezvous channel.
+    pub fn lock_free(mut self) -> Self {
+        self.lock_free = true;
+        self
+    }
+
+    /// Collects the counters read through [`Sender::stats`] and [`Reciever::stats`].
+    pub fn stats(mut self) -> Self {
+        self.stats = true;
+        self
+    }
+
+    /// Records how long each message waited in the channel, read through [`Sender::latency`]
+    /// and [`Reciever::latency`]. Every message gets timestamped when sent, without this
+    /// option nothing is timestamped at all.
+    ///
+    /// Can't be combined with [`lock_free`](Builder::lock_free), see [`build`](Builder::build).
+    /// Needs the `std` feature for its clock.
+    #[cfg(feature = "std")]
+    pub fn latency(mut self) -> Self {
+        self.latency = true;
+        self
+    }
+
+    /// Creates the channel.
+    ///
+    /// # Panics
+    ///
+    /// If [`latency`](Builder::latency) is combined with a lock-free queue, whose slots have no
+    /// room for the timestamps.
+    pub fn build<T>(self) -> (Sender<T>, Reciever<T>) {
+        let queue = match self.cap {
+            _ if !self.lock_free => None,
+            None => Some(Queue::List(List::new())),
+            Some(0) => None,
+            Some(cap) => Some(Queue::Array(Array::new(cap))),
+        };
+        assert!(
+            !self.latency || queue.is_none(),
+            "latency tracking isn't supported on lock-free channels"
+        );
+        let mut inner = Inner::new(self.cap, queue);
+        inner.stats = self.stats.then(Stats::default);
+        inner.latency = self.latency.then(Latency::new);
+        crate::channel(inner)
+    }
+}
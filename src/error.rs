@@ -0,0 +1,232 @@
+#[cfg(test)]
+use alloc::{boxed::Box, format, string::ToString};
+use core::{error::Error, fmt};
+
+/// Returned by [`Sender::send`](crate::Sender::send) when the reciever is gone. Hands back the
+/// value which couldn't be sent.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct SendErr<T>(pub T);
+
+// implemented by hand so that `T` doesn't need to be `Debug`.
+impl<T> fmt::Debug for SendErr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SendErr").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendErr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sending on a closed channel")
+    }
+}
+
+impl<T> Error for SendErr<T> {}
+
+/// Returned by [`Reciever::recv`](crate::Reciever::recv) when the channel is empty and every
+/// sender is gone.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RecvError;
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("receiving on an empty and disconnected channel")
+    }
+}
+
+impl Error for RecvError {}
+
+/// Returned by [`Sender::try_send`](crate::Sender::try_send). Hands back the value which
+/// couldn't be sent.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum TrySendError<T> {
+    /// The channel has no room for the value right now.
+    Full(T),
+    /// The reciever is gone.
+    Disconnected(T),
+}
+
+impl<T> TrySendError<T> {
+    /// Returns the value which couldn't be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(v) | Self::Disconnected(v) => v,
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("Full(..)"),
+            Self::Disconnected(_) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("sending on a full channel"),
+            Self::Disconnected(_) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+impl<T> From<SendErr<T>> for TrySendError<T> {
+    fn from(err: SendErr<T>) -> Self {
+        Self::Disconnected(err.0)
+    }
+}
+
+/// Returned by [`Reciever::try_recv`](crate::Reciever::try_recv).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TryRecvError {
+    /// No message is ready right now.
+    Empty,
+    /// The channel is empty and every sender is gone.
+    Disconnected,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("receiving on an empty channel"),
+            Self::Disconnected => f.write_str("receiving on an empty and disconnected channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+impl From<RecvError> for TryRecvError {
+    fn from(_: RecvError) -> Self {
+        Self::Disconnected
+    }
+}
+
+/// Returned by [`Sender::send_timeout`](crate::Sender::send_timeout) and
+/// [`Sender::send_deadline`](crate::Sender::send_deadline). Hands back the value which couldn't
+/// be sent.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SendTimeoutError<T> {
+    /// The channel stayed full untill the deadline.
+    Timeout(T),
+    /// The reciever is gone.
+    Disconnected(T),
+}
+
+impl<T> SendTimeoutError<T> {
+    /// Returns the value which couldn't be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Timeout(v) | Self::Disconnected(v) => v,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout(_) => f.write_str("Timeout(..)"),
+            Self::Disconnected(_) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout(_) => f.write_str("timed out waiting on send operation"),
+            Self::Disconnected(_) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for SendTimeoutError<T> {}
+
+impl<T> From<SendErr<T>> for SendTimeoutError<T> {
+    fn from(err: SendErr<T>) -> Self {
+        Self::Disconnected(err.0)
+    }
+}
+
+/// Returned by [`Reciever::recv_timeout`](crate::Reciever::recv_timeout) and
+/// [`Reciever::recv_deadline`](crate::Reciever::recv_deadline).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RecvTimeoutError {
+    /// No message arrived untill the deadline.
+    Timeout,
+    /// The channel is empty and every sender is gone.
+    Disconnected,
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("timed out waiting on receive operation"),
+            Self::Disconnected => f.write_str("receiving on an empty and disconnected channel"),
+        }
+    }
+}
+
+impl Error for RecvTimeoutError {}
+
+impl From<RecvError> for RecvTimeoutError {
+    fn from(_: RecvError) -> Self {
+        Self::Disconnected
+    }
+}
+
+/// Returned by [`Select::try_ready`](crate::Select::try_ready) when none of the operations is
+/// ready.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TryReadyError;
+
+impl fmt::Display for TryReadyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("all operations in the selection would block")
+    }
+}
+
+impl Error for TryReadyError {}
+
+/// Returned by [`Select::ready_timeout`](crate::Select::ready_timeout) and
+/// [`Select::ready_deadline`](crate::Select::ready_deadline) when none of the operations became
+/// ready in time.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ReadyTimeoutError;
+
+impl fmt::Display for ReadyTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("timed out waiting on select")
+    }
+}
+
+impl Error for ReadyTimeoutError {}
+
+#[test]
+fn send_err_debug_hides_value() {
+    struct NoDebug;
+    assert_eq!(format!("{:?}", SendErr(NoDebug)), "SendErr(..)");
+}
+
+#[test]
+fn errors_box_into_dyn_error() {
+    fn send() -> Result<(), Box<dyn Error + Send + Sync>> {
+        Err(SendErr(1))?
+    }
+    fn recv() -> Result<(), Box<dyn Error + Send + Sync>> {
+        Err(RecvError)?
+    }
+
+    assert_eq!(
+        send().unwrap_err().to_string(),
+        "sending on a closed channel"
+    );
+    assert_eq!(
+        recv().unwrap_err().to_string(),
+        "receiving on an empty and disconnected channel"
+    );
+}
@@ -0,0 +1,463 @@
+#[cfg(test)]
+use alloc::vec::Vec;
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{
+    lock,
+    lock_free::{PushError, Queue},
+    waker::Woken,
+    Reciever, RecvError, SendErr, Sender,
+};
+
+impl<T> Sender<T> {
+    /// Sends a value from async code, the async counterpart of [`send`](Sender::send): on a
+    /// bounded channel the returned future waits for room (for a rendezvous channel, for a
+    /// reciever to take the value) without blocking the thread.
+    pub fn send_async(&self, val: T) -> SendFut<'_, T> {
+        SendFut {
+            tx: self,
+            val: Some(val),
+            ticket: None,
+            waker: None,
+        }
+    }
+
+    /// The async counterpart of [`closed`](Sender::closed), resolves once the reciever is gone
+    /// or closed the channel.
+    pub fn closed_async(&self) -> ClosedFut<'_, T> {
+        ClosedFut {
+            tx: self,
+            waker: None,
+        }
+    }
+}
+
+impl<T> Reciever<T> {
+    /// Recieves a message from async code, the async counterpart of [`recv`](Reciever::recv).
+    pub fn recv_async(&mut self) -> RecvFut<'_, T> {
+        RecvFut { rx: self }
+    }
+
+    /// Polls for the next message, registering the task's waker if none is ready yet.
+    ///
+    /// Like a `Stream`, returns `None` once the channel is empty and every sender is gone.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if let Some(v) = self.pop_local() {
+            if self.waker.is_some() {
+                // don't leave a stale registration behind, lock-free senders would keep taking
+                // the mutex to wake us.
+                let mut guard = lock(&self.inner.mu);
+                self.inner.unwatch_recv(&mut guard, &mut self.waker);
+            }
+            return Poll::Ready(Some(v));
+        }
+
+        let mut guard = lock(&self.inner.mu);
+        let done = guard.done;
+        let mut woken = Woken::default();
+        let res = match self.inner.take(
+            &mut guard,
+            &mut self.local_buf,
+            &mut self.local_stamps,
+            &mut woken,
+        ) {
+            Some(v) => Some(v),
+            None if done => None,
+            None => {
+                // registered while holding the lock, so the next send can't slip by unnoticed.
+                self.inner
+                    .watch_recv(&mut guard, &mut self.waker, cx.waker());
+                // lock-free senders don't take the lock though, re-check now that they can see
+                // our registration.
+                if self.inner.queue.is_none() {
+                    return Poll::Pending;
+                }
+                match self.inner.take(
+                    &mut guard,
+                    &mut self.local_buf,
+                    &mut self.local_stamps,
+                    &mut woken,
+                ) {
+                    Some(v) => Some(v),
+                    None => return Poll::Pending,
+                }
+            }
+        };
+        self.inner.unwatch_recv(&mut guard, &mut self.waker);
+        drop(guard);
+        woken.wake();
+        Poll::Ready(res)
+    }
+}
+
+/// Future returned by [`Reciever::recv_async`].
+///
+/// Dropping it before it completes cancels the recieve, the reciever stops waiting for a message.
+pub struct RecvFut<'a, T> {
+    rx: &'a mut Reciever<T>,
+}
+
+impl<T> Future for RecvFut<'_, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.rx.poll_recv(cx).map(|v| v.ok_or(RecvError))
+    }
+}
+
+impl<T> Drop for RecvFut<'_, T> {
+    fn drop(&mut self) {
+        // a cancelled recv must not keep counting as a waiting taker.
+        if self.rx.waker.is_some() {
+            let mut guard = lock(&self.rx.inner.mu);
+            self.rx.inner.unwatch_recv(&mut guard, &mut self.rx.waker);
+        }
+    }
+}
+
+/// Future returned by [`Sender::send_async`].
+///
+/// Dropping it before it completes cancels the send, a value still waiting in the hand-off slot
+/// of a rendezvous channel is taken back out.
+pub struct SendFut<'a, T> {
+    tx: &'a Sender<T>,
+    val: Option<T>,
+    // set once our value sits in the hand-off slot of a rendezvous channel, see
+    // `Critical::taken`.
+    ticket: Option<usize>,
+    // our entry in `Critical::send_wakers`.
+    waker: Option<usize>,
+}
+
+// the value is never pinned, it just gets moved into the channel.
+impl<T> Unpin for SendFut<'_, T> {}
+
+impl<T> Future for SendFut<'_, T> {
+    type Output = Result<(), SendErr<T>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let tx = this.tx;
+        if let Some(queue) = &tx.inner.queue {
+            return this.poll_push(queue, cx);
+        }
+
+        let mut guard = lock(&tx.inner.mu);
+
+        if let Some(ticket) = this.ticket {
+            // rendezvous, our value is in the slot untill `taken` moves past our ticket.
+            if guard.taken != ticket {
+                this.ticket = None;
+                tx.inner.unwatch_send(&mut guard, &mut this.waker);
+                return Poll::Ready(Ok(()));
+            }
+            if guard.done {
+                this.ticket = None;
+                tx.inner.unwatch_send(&mut guard, &mut this.waker);
+                return Poll::Ready(Err(SendErr(guard.slot.take().unwrap())));
+            }
+            tx.inner.watch_send(&mut guard, &mut this.waker, cx.waker());
+            return Poll::Pending;
+        }
+
+        let val = this.val.take().expect("SendFut polled after completion");
+        if guard.done {
+            tx.inner.unwatch_send(&mut guard, &mut this.waker);
+            return Poll::Ready(Err(SendErr(val)));
+        }
+
+        if tx.inner.cap == Some(0) {
+            if guard.slot.is_some() {
+                // another sender is mid hand-off.
+                this.val = Some(val);
+                tx.inner.watch_send(&mut guard, &mut this.waker, cx.waker());
+                return Poll::Pending;
+            }
+
+            tx.inner.fill_slot(&mut guard, val);
+            this.ticket = Some(guard.taken);
+            tx.inner.watch_send(&mut guard, &mut this.waker, cx.waker());
+            let mut woken = Woken::default();
+            woken.add(&guard.recv_wakers);
+            drop(guard);
+            tx.inner.cond.notify_one();
+            woken.wake();
+            return Poll::Pending;
+        }
+
+        if tx.inner.is_full(&guard) {
+            tx.inner.watch_send(&mut guard, &mut this.waker, cx.waker());
+            // re-check after registering, the reciever might have consumed from its local
+            // buffer in between without seeing us.
+            if tx.inner.is_full(&guard) {
+                this.val = Some(val);
+                return Poll::Pending;
+            }
+        }
+        tx.inner.unwatch_send(&mut guard, &mut this.waker);
+        tx.inner.push(&mut guard, val);
+        let mut woken = Woken::default();
+        woken.add(&guard.recv_wakers);
+        drop(guard);
+        tx.inner.cond.notify_one();
+        woken.wake();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> SendFut<'_, T> {
+    // `poll` of a lock-free channel, only takes the mutex to wait for room.
+    fn poll_push(
+        &mut self,
+        queue: &Queue<T>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), SendErr<T>>> {
+        let tx = self.tx;
+        let mut val = self.val.take().expect("SendFut polled after completion");
+        let mut guard = None;
+        let res = loop {
+            match queue.push(val) {
+                Ok(()) => {
+                    tx.inner.record_sent(1, || queue.len());
+                    break Ok(());
+                }
+                Err(PushError::Closed(v)) => break Err(SendErr(v)),
+                Err(PushError::Full(v)) if guard.is_some() => {
+                    self.val = Some(v);
+                    return Poll::Pending;
+                }
+                Err(PushError::Full(v)) => {
+                    let guard = guard.insert(lock(&tx.inner.mu));
+                    tx.inner.watch_send(guard, &mut self.waker, cx.waker());
+                    // re-try after registering, the reciever might have popped in between
+                    // without seeing us.
+                    val = v;
+                }
+            }
+        };
+
+        if self.waker.is_some() {
+            let mut guard = guard.unwrap_or_else(|| lock(&tx.inner.mu));
+            tx.inner.unwatch_send(&mut guard, &mut self.waker);
+        }
+        if res.is_ok() {
+            tx.inner.wake_sleepers();
+        }
+        Poll::Ready(res)
+    }
+}
+
+impl<T> Drop for SendFut<'_, T> {
+    fn drop(&mut self) {
+        if self.ticket.is_none() && self.waker.is_none() {
+            return;
+        }
+
+        let inner = &self.tx.inner;
+        let mut guard = lock(&inner.mu);
+        inner.unwatch_send(&mut guard, &mut self.waker);
+        if self.ticket.take() == Some(guard.taken) {
+            // cancelled before a reciever took our value, take it back out of the slot. It is
+            // dropped after unlocking, like the rest of the future.
+            let val = guard.slot.take();
+            let mut woken = Woken::default();
+            woken.add(&guard.send_wakers);
+            drop(guard);
+            inner.space.notify_all();
+            woken.wake_in_drop();
+            drop(val);
+        }
+    }
+}
+
+/// Future returned by [`Sender::closed_async`].
+pub struct ClosedFut<'a, T> {
+    tx: &'a Sender<T>,
+    // our entry in `Critical::closed_wakers`.
+    waker: Option<usize>,
+}
+
+impl<T> Future for ClosedFut<'_, T> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = &mut *self;
+        let mut guard = lock(&this.tx.inner.mu);
+        if guard.done {
+            guard.closed_wakers.unregister(&mut this.waker);
+            return Poll::Ready(());
+        }
+        guard.closed_wakers.register(&mut this.waker, cx.waker());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for ClosedFut<'_, T> {
+    fn drop(&mut self) {
+        if self.waker.is_some() {
+            let mut guard = lock(&self.tx.inner.mu);
+            guard.closed_wakers.unregister(&mut self.waker);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn block_on<F: Future>(fut: F) -> F::Output {
+    use std::{
+        sync::Arc,
+        task::{Wake, Waker},
+        thread::{self, Thread},
+    };
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = std::pin::pin!(fut);
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(v) => return v,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[test]
+fn recv_async_from_sync_sender() {
+    let (tx, mut rx) = crate::unbounded();
+    let handle = std::thread::spawn(move || {
+        for i in 0..100 {
+            std::thread::sleep(std::time::Duration::from_micros(100));
+            tx.send(i).unwrap();
+        }
+    });
+
+    let got = block_on(async {
+        let mut got = Vec::new();
+        while let Ok(v) = rx.recv_async().await {
+            got.push(v);
+        }
+        got
+    });
+    assert_eq!(got, (0..100).collect::<Vec<_>>());
+    handle.join().unwrap();
+}
+
+#[test]
+fn poll_recv_pending_then_ready() {
+    let (tx, mut rx) = crate::unbounded();
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+    assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+    tx.send(1).unwrap();
+    assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Some(1)));
+    drop(tx);
+    assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(None));
+}
+
+#[test]
+fn send_async_waits_for_room() {
+    let (tx, mut rx) = crate::bounded(1);
+    let handle = std::thread::spawn(move || {
+        block_on(async {
+            for i in 0..50 {
+                tx.send_async(i).await.unwrap();
+            }
+        })
+    });
+
+    for i in 0..50 {
+        assert_eq!(rx.recv(), Ok(i));
+    }
+    handle.join().unwrap();
+    assert_eq!(rx.recv(), Err(RecvError));
+}
+
+#[test]
+fn send_async_rendezvous() {
+    let (tx, mut rx) = crate::rendezvous();
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+    let mut fut = tx.send_async(1);
+    // waits for a taker even though the value is already in the slot.
+    assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+    assert_eq!(rx.try_recv(), Ok(1));
+    assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Ready(Ok(())));
+
+    // a cancelled hand-off takes its value back.
+    let mut fut = tx.send_async(2);
+    assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+    drop(fut);
+    assert_eq!(rx.try_recv(), Err(crate::TryRecvError::Empty));
+
+    drop(rx);
+    assert_eq!(block_on(tx.send_async(3)), Err(SendErr(3)));
+}
+
+#[test]
+fn async_reciever_is_no_taker_for_try_send() {
+    let (tx, mut rx) = crate::rendezvous();
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+    let mut fut = rx.recv_async();
+    assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+    // the future might still get cancelled, which would strand the value in the slot.
+    assert_eq!(tx.try_send(1), Err(crate::TrySendError::Full(1)));
+
+    // a blocking send waits for the future to take it instead.
+    let handle = std::thread::spawn(move || tx.send(2));
+    assert_eq!(block_on(fut), Ok(2));
+    handle.join().unwrap().unwrap();
+}
+
+#[test]
+fn closed_async_resolves_on_reciever_drop() {
+    let (tx, rx) = crate::unbounded::<()>();
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+    let mut fut = tx.closed_async();
+    assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        drop(rx);
+    });
+    block_on(fut);
+    handle.join().unwrap();
+    assert!(tx.is_closed());
+}
+
+#[test]
+fn cancelled_recv_async_is_no_taker() {
+    let (tx, mut rx) = crate::rendezvous();
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+    let mut fut = rx.recv_async();
+    assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+    drop(fut);
+    assert_eq!(tx.try_send(1), Err(crate::TrySendError::Full(1)));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn cancelled_recv_async_strands_no_value() {
+    let (tx, mut rx) = crate::rendezvous();
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+    let mut fut = rx.recv_async();
+    assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+    assert!(tx.try_send(1).is_err());
+    drop(fut);
+    assert_eq!(tx.len(), 0);
+
+    // the next hand-off goes straight through, there is no orphaned value ahead of it.
+    let handle = std::thread::spawn(move || rx.recv());
+    assert_eq!(
+        tx.send_timeout(2, std::time::Duration::from_secs(5)),
+        Ok(())
+    );
+    assert_eq!(handle.join().unwrap(), Ok(2));
+}
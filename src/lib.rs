@@ -1,48 +1,707 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(unused)]
 
-use std::{
+extern crate alloc;
+// the tests use threads and clocks even when the crate itself is built without std.
+#[cfg(test)]
+extern crate std;
+
+pub mod broadcast;
+mod builder;
+mod error;
+mod future;
+pub mod lock_free;
+mod oneshot;
+pub mod priority;
+mod select;
+mod stats;
+mod sync;
+mod waker;
+pub mod watch;
+
+pub use broadcast::broadcast;
+pub use builder::Builder;
+pub use error::{
+    ReadyTimeoutError, RecvError, RecvTimeoutError, SendErr, SendTimeoutError, TryReadyError,
+    TryRecvError, TrySendError,
+};
+pub use future::{ClosedFut, RecvFut, SendFut};
+pub use oneshot::{oneshot, OneshotReciever, OneshotSender};
+pub use priority::priority;
+pub use select::Select;
+pub use stats::ChannelStats;
+#[cfg(feature = "std")]
+pub use stats::LatencyHistogram;
+pub use watch::watch;
+
+use lock_free::{PushError, Queue};
+use stats::{Latency, Stats};
+use sync::{lock, timed_out, wait, Condvar, Instant, Mutex, MutexGuard};
+use waker::{Wakers, Woken};
+
+use alloc::{
     collections::VecDeque,
-    mem,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    iter, mem,
     ops::DerefMut,
-    sync::{Arc, Condvar, Mutex},
-    thread,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::Waker,
+    time::Duration,
 };
 
+#[cfg(test)]
+use alloc::vec;
+#[cfg(test)]
+use std::{println, thread};
+
 struct Inner<T> {
     mu: Mutex<Critical<T>>,
     cond: Condvar,
+    // senders waiting for free capacity on a bounded channel park here, separate from `cond` so
+    // that a notify_one meant for the reciever never gets swallowed by a sender.
+    space: Condvar,
+    // senders waiting in `Sender::closed` park here, they only care about the reciever going
+    // away.
+    closed: Condvar,
+    // maximum number of messages in flight, `None` for unbounded channels. `Some(0)` makes a
+    // rendezvous channel where messages go through `Critical::slot` instead of `Critical::buf`.
+    cap: Option<usize>,
+    // messages swapped into the recievers' local buffers which haven't been consumed yet, so
+    // that `len` can see them. On bounded channels they still count against `cap`.
+    //
+    // this lives outside the mutex since the reciever consumes its local buffer without locking.
+    held: AtomicUsize,
+    // number of senders parked on `space` plus the ones registered in `Critical::send_wakers`,
+    // lets the reciever skip the mutex when nobody waits.
+    blocked: AtomicUsize,
+    // lock-free queue holding the messages instead of `Critical::buf`, see `lock_free`.
+    queue: Option<Queue<T>>,
+    // recievers of a lock-free channel parked on `cond` or registered in
+    // `Critical::recv_wakers`, lets senders skip the mutex when nobody waits.
+    sleeping: AtomicUsize,
+    // opt-in counters, see `Builder::stats`.
+    stats: Option<Stats>,
+    // opt-in latency histogram, see `Builder::latency`. Only ever set on channels going through
+    // `Critical::buf`.
+    latency: Option<Latency>,
+}
+
+impl<T> Inner<T> {
+    fn new(cap: Option<usize>, queue: Option<Queue<T>>) -> Self {
+        Self {
+            mu: Mutex::new(Critical {
+                buf: VecDeque::default(),
+                stamps: VecDeque::default(),
+                slot: None,
+                slot_stamp: None,
+                taken: 0,
+                waiting: 0,
+                senders: 1,
+                receivers: 1,
+                done: false,
+                recv_wakers: Wakers::new(),
+                send_wakers: Wakers::new(),
+                closed_wakers: Wakers::new(),
+            }),
+            cond: Condvar::default(),
+            space: Condvar::default(),
+            closed: Condvar::default(),
+            cap,
+            held: AtomicUsize::new(0),
+            blocked: AtomicUsize::new(0),
+            queue,
+            sleeping: AtomicUsize::new(0),
+            stats: None,
+            latency: None,
+        }
+    }
+
+    fn is_full(&self, guard: &Critical<T>) -> bool {
+        if let Some(queue) = &self.queue {
+            return queue.is_full();
+        }
+
+        match self.cap {
+            Some(cap) => guard.buf.len() + self.held.load(Ordering::SeqCst) >= cap,
+            None => false,
+        }
+    }
+
+    // number of messages in flight, including the ones in the recievers' local buffers.
+    fn len(&self) -> usize {
+        if let Some(queue) = &self.queue {
+            return queue.len();
+        }
+
+        let guard = lock(&self.mu);
+        self.depth(&guard)
+    }
+
+    // `len` for the mutex-backed channels, while already holding the lock.
+    fn depth(&self, guard: &Critical<T>) -> usize {
+        guard.buf.len() + self.held.load(Ordering::SeqCst) + usize::from(guard.slot.is_some())
+    }
+
+    // `depth` is only evaluated if stats are enabled.
+    fn record_sent(&self, n: usize, depth: impl FnOnce() -> usize) {
+        if let Some(stats) = &self.stats {
+            stats.sent(n, depth());
+        }
+    }
+
+    fn record_received(&self, n: usize) {
+        if let Some(stats) = &self.stats {
+            stats.received(n);
+        }
+    }
+
+    // `stamp` is when the recieved message was sent, `None` unless latency is tracked.
+    fn record_latency(&self, stamp: Option<Instant>) {
+        if let (Some(latency), Some(stamp)) = (&self.latency, stamp) {
+            latency.record(stamp.elapsed());
+        }
+    }
+
+    // queues a message on `Critical::buf`, the caller already checked for room.
+    fn push(&self, guard: &mut Critical<T>, val: T) {
+        guard.buf.push_front(val);
+        // without std there's no clock, nor a way to enable latency tracking.
+        #[cfg(feature = "std")]
+        if self.latency.is_some() {
+            guard.stamps.push_front(Instant::now());
+        }
+        self.record_sent(1, || self.depth(guard));
+    }
+
+    // places a message in the hand-off slot of a rendezvous channel, the caller already checked
+    // that it's free.
+    fn fill_slot(&self, guard: &mut Critical<T>, val: T) {
+        guard.slot = Some(val);
+        #[cfg(feature = "std")]
+        if self.latency.is_some() {
+            guard.slot_stamp = Some(Instant::now());
+        }
+    }
+
+    // `wait` which accounts the time spent parked in the stats. Without std there is no clock to
+    // measure it with.
+    fn park<'a>(
+        &self,
+        cond: &Condvar,
+        guard: MutexGuard<'a, Critical<T>>,
+        deadline: Option<Instant>,
+    ) -> MutexGuard<'a, Critical<T>> {
+        #[cfg(feature = "std")]
+        if let Some(stats) = &self.stats {
+            let start = Instant::now();
+            let guard = wait(cond, guard, deadline);
+            stats.blocked(start.elapsed());
+            return guard;
+        }
+        wait(cond, guard, deadline)
+    }
+
+    // takes the next message out of the shared state, refilling the reciever's local buffer on
+    // the way. `local_stamps` follows `local_buf` like `Critical::stamps` follows `Critical::buf`.
+    // The senders to wake go into `woken`, for after the caller unlocked.
+    fn take(
+        &self,
+        guard: &mut Critical<T>,
+        local_buf: &mut VecDeque<T>,
+        local_stamps: &mut VecDeque<Instant>,
+        woken: &mut Woken,
+    ) -> Option<T> {
+        if let Some(queue) = &self.queue {
+            let v = queue.pop()?;
+            self.popped(guard, woken);
+            return Some(v);
+        }
+
+        if let Some(v) = guard.slot.take() {
+            // rendezvous hand-off, wake up the sender waiting on us and the ones waiting for the
+            // slot. It only counts as sent once taken, the sender might still take it back.
+            guard.taken += 1;
+            self.record_sent(1, || 1);
+            self.record_received(1);
+            self.record_latency(guard.slot_stamp.take());
+            self.space.notify_all();
+            woken.add(&guard.send_wakers);
+            return Some(v);
+        }
+
+        // message on queue, recieve it and return it.
+        let v = guard.buf.pop_back()?;
+        self.record_received(1);
+        self.record_latency(guard.stamps.pop_back());
+        if guard.receivers == 1 {
+            // swap our local (empty) buffer (which has an allocated capacity) with the incoming
+            // buffer.
+            //
+            // this will keep some data local taking advantage that we only have one reciever,
+            // this data we can acess without interacting with the mutex.
+            mem::swap(local_buf, &mut guard.buf);
+            mem::swap(local_stamps, &mut guard.stamps);
+        } else {
+            // with several recievers stealing the whole queue would starve the others, so only
+            // take our fair share of the backlog (the oldest messages sit at the back).
+            let share = guard.buf.len() / guard.receivers;
+            let at = guard.buf.len() - share;
+            local_buf.extend(guard.buf.drain(at..));
+            if self.latency.is_some() {
+                local_stamps.extend(guard.stamps.drain(at..));
+            }
+            if !guard.buf.is_empty() && guard.waiting > 0 {
+                // pass the leftovers on to another parked reciever.
+                self.cond.notify_one();
+            }
+        }
+        self.held.fetch_add(local_buf.len(), Ordering::SeqCst);
+        if self.cap.is_some() {
+            // the swapped messages still occupy capacity untill we consume them, only `v` frees
+            // up a slot.
+            self.space.notify_one();
+            woken.add(&guard.send_wakers);
+        }
+        Some(v)
+    }
+
+    // accounts for a message popped off the lock-free queue while holding the mutex.
+    fn popped(&self, guard: &mut Critical<T>, woken: &mut Woken) {
+        self.record_received(1);
+        if self.blocked.load(Ordering::SeqCst) > 0 {
+            // we hold the mutex, so a sender re-trying the push can't park before this.
+            self.space.notify_one();
+            woken.add(&guard.send_wakers);
+        }
+    }
+
+    // registers a waker to be woken once a message might be available.
+    fn watch_recv(&self, guard: &mut Critical<T>, id: &mut Option<usize>, waker: &Waker) {
+        if !guard.recv_wakers.register(id, waker) {
+            return;
+        }
+
+        if self.queue.is_some() {
+            self.sleeping.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn unwatch_recv(&self, guard: &mut Critical<T>, id: &mut Option<usize>) {
+        let waker = guard.recv_wakers.unregister(id);
+        if waker.is_some() && self.queue.is_some() {
+            self.sleeping.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    // registers a waker to be woken once there might be room for a message.
+    fn watch_send(&self, guard: &mut Critical<T>, id: &mut Option<usize>, waker: &Waker) {
+        if guard.send_wakers.register(id, waker) {
+            // count as a blocked sender, so that the reciever wakes us when it frees up capacity
+            // by consuming its local buffer.
+            self.blocked.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn unwatch_send(&self, guard: &mut Critical<T>, id: &mut Option<usize>) {
+        let waker = guard.send_wakers.unregister(id);
+        if waker.is_some() {
+            self.blocked.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    // closes the channel from the recieving side: sends fail from now on, but the messages
+    // already queued can still be recieved. Returns the wakers to wake, the caller knows whether
+    // that happens in `Drop`.
+    #[must_use]
+    fn close(&self, mut guard: MutexGuard<'_, Critical<T>>) -> Woken {
+        // set done to true to stop senders from blocking.
+        guard.done = true;
+        if let Some(queue) = &self.queue {
+            queue.close();
+        }
+        let mut woken = Woken::default();
+        woken.add(&guard.send_wakers);
+        woken.add(&guard.closed_wakers);
+        // other recievers must not wait for messages that can't come anymore.
+        woken.add(&guard.recv_wakers);
+        drop(guard);
+        self.space.notify_all(); // wake up senders blocked on a full channel.
+        self.closed.notify_all();
+        self.cond.notify_all();
+        woken
+    }
+
+    // called by senders after pushing onto a lock-free queue.
+    fn wake_sleepers(&self) {
+        // a reciever announces itself in `sleeping` before re-checking the queue, so either it
+        // sees our message or we see its announcement (both sides use SeqCst).
+        if self.sleeping.load(Ordering::SeqCst) > 0 {
+            // the reciever holds the mutex from its re-check untill it is parked on `cond`, so
+            // acquiring it here guarantees the notification isn't lost.
+            let mut woken = Woken::default();
+            woken.add(&lock(&self.mu).recv_wakers);
+            self.cond.notify_one();
+            woken.wake();
+        }
+    }
+
+    // called by the reciever after consuming `n` messages from its local buffer.
+    fn release_held(&self, n: usize) {
+        if n == 0 {
+            return;
+        }
+
+        self.held.fetch_sub(n, Ordering::SeqCst);
+        if self.cap.is_some() {
+            self.wake_blocked();
+        }
+    }
+
+    // called by the reciever after freeing up capacity without holding the mutex.
+    fn wake_blocked(&self) {
+        // a sender announces itself in `blocked` before re-checking the capacity, so either it
+        // sees the freed slot or we see its announcement (both sides use SeqCst).
+        if self.blocked.load(Ordering::SeqCst) > 0 {
+            // the sender holds the mutex from its re-check untill it is parked on `space`, so
+            // acquiring it here guarantees the notification isn't lost.
+            let mut woken = Woken::default();
+            woken.add(&lock(&self.mu).send_wakers);
+            self.space.notify_one();
+            woken.wake();
+        }
+    }
 }
 
 struct Critical<T> {
     buf: VecDeque<T>,
+    // send times of the messages in `buf`, in the same order. Stays empty unless latency is
+    // tracked.
+    stamps: VecDeque<Instant>,
+    // hand-off slot of a rendezvous channel, holds the value of the one sender currently waiting
+    // for a taker.
+    slot: Option<T>,
+    // send time of the value in `slot`, only set if latency is tracked.
+    slot_stamp: Option<Instant>,
+    // number of values taken out of `slot`, lets a sender tell that its own value got taken even
+    // if another sender refilled the slot since.
+    taken: usize,
+    // number of recievers parked on `cond`.
+    waiting: usize,
     senders: usize,
+    // number of live (cloned) recievers, the channel turns mpmc once this goes above 1.
+    receivers: usize,
+    // set once all senders or all recievers are gone, or the reciever closed the channel. Sends
+    // fail from then on while recievers drain what's left.
     done: bool,
+    // wakers of async recievers and `Select` calls waiting for a message (or disconnect) on this
+    // channel, the async counterpart of `Inner::cond`.
+    recv_wakers: Wakers,
+    // wakers of async senders and `Select` calls waiting for room (or disconnect) on this
+    // channel, the async counterpart of `Inner::space`.
+    send_wakers: Wakers,
+    // wakers of async senders waiting for the reciever to go away, the async counterpart of
+    // `Inner::closed`.
+    closed_wakers: Wakers,
 }
 
-pub struct SendErr<T>(pub T);
+impl<T> Critical<T> {
+    // whether a rendezvous hand-off would be picked up right away. Only a reciever parked in
+    // `recv` is sure to take the value, a registered future or `Select` might still give up and
+    // leave it stranded in the slot.
+    fn has_takers(&self) -> bool {
+        self.waiting > 0
+    }
+}
 
+/// The sending half of a channel, can be cloned to send from multiple threads.
 pub struct Sender<T> {
     inner: Arc<Inner<T>>,
 }
 
 impl<T> Sender<T> {
-    fn send(&self, val: T) -> Result<(), T> {
+    /// Sends a value down the channel, blocking while a bounded channel is full (or, for a
+    /// rendezvous channel, untill a reciever takes it).
+    ///
+    /// Fails with [`SendErr`] holding the value if the reciever is gone.
+    pub fn send(&self, val: T) -> Result<(), SendErr<T>> {
+        self.send_until(val, None).map_err(|err| match err {
+            SendTimeoutError::Disconnected(v) => SendErr(v),
+            SendTimeoutError::Timeout(_) => unreachable!("send without a deadline timed out"),
+        })
+    }
+
+    /// Like [`send`](Sender::send) but gives up once `timeout` elapsed, handing the value back
+    /// in [`SendTimeoutError::Timeout`].
+    ///
+    /// Only bounded channels can time out, unbounded ones never block.
+    #[cfg(feature = "std")]
+    pub fn send_timeout(&self, val: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        self.send_until(val, Instant::now().checked_add(timeout))
+    }
+
+    /// Like [`send_timeout`](Sender::send_timeout) but waits untill an absolute `deadline`.
+    #[cfg(feature = "std")]
+    pub fn send_deadline(&self, val: T, deadline: Instant) -> Result<(), SendTimeoutError<T>> {
+        self.send_until(val, Some(deadline))
+    }
+
+    fn send_until(&self, val: T, deadline: Option<Instant>) -> Result<(), SendTimeoutError<T>> {
+        if let Some(queue) = &self.inner.queue {
+            return self.push_until(queue, val, deadline);
+        }
+
         // acquire mutex, add a value to the send queue and signal to potential recievers waiting.
-        let mut guard = self.inner.mu.lock().unwrap();
+        let mut guard = lock(&self.inner.mu);
+        if self.inner.cap == Some(0) {
+            return self.hand_off(guard, val, deadline);
+        }
+
+        // on a bounded channel wait for room first.
+        while !guard.done && self.inner.is_full(&guard) {
+            if timed_out(deadline) {
+                return Err(SendTimeoutError::Timeout(val));
+            }
+
+            self.inner.blocked.fetch_add(1, Ordering::SeqCst);
+            // re-check after announcing ourselves, the reciever might have consumed from its
+            // local buffer in between without seeing us.
+            if !self.inner.is_full(&guard) {
+                self.inner.blocked.fetch_sub(1, Ordering::SeqCst);
+                break;
+            }
+            guard = self.inner.park(&self.inner.space, guard, deadline);
+            self.inner.blocked.fetch_sub(1, Ordering::SeqCst);
+        }
         if guard.done {
-            return Err(val);
+            return Err(SendTimeoutError::Disconnected(val));
         }
-        guard.buf.push_front(val);
+        self.inner.push(&mut guard, val);
+        let mut woken = Woken::default();
+        woken.add(&guard.recv_wakers);
         drop(guard); // drop guard since we need the reciever to be able to acquire it after the
                      // signal.
         self.inner.cond.notify_one(); // notify the only one possible listener.
+        woken.wake();
+        Ok(())
+    }
+
+    /// Attempts to send a value without blocking.
+    ///
+    /// Fails with [`TrySendError::Full`] if a bounded channel has no room, or if no reciever is
+    /// currently blocked in [`recv`](Reciever::recv) on a rendezvous channel, and with
+    /// [`TrySendError::Disconnected`] if the reciever is gone. Both hand back the value. Pending
+    /// `recv_async` futures don't count as waiting on a rendezvous channel, they could still be
+    /// cancelled without taking the value.
+    pub fn try_send(&self, val: T) -> Result<(), TrySendError<T>> {
+        if let Some(queue) = &self.inner.queue {
+            return match queue.push(val) {
+                Ok(()) => {
+                    self.inner.record_sent(1, || queue.len());
+                    self.inner.wake_sleepers();
+                    Ok(())
+                }
+                Err(PushError::Full(v)) => Err(TrySendError::Full(v)),
+                Err(PushError::Closed(v)) => Err(TrySendError::Disconnected(v)),
+            };
+        }
+
+        let mut guard = lock(&self.inner.mu);
+        if guard.done {
+            return Err(TrySendError::Disconnected(val));
+        }
+
+        if self.inner.cap == Some(0) {
+            // we can't wait for a taker, so only hand off to a reciever which is already parked.
+            if guard.slot.is_some() || !guard.has_takers() {
+                return Err(TrySendError::Full(val));
+            }
+            self.inner.fill_slot(&mut guard, val);
+        } else if self.inner.is_full(&guard) {
+            return Err(TrySendError::Full(val));
+        } else {
+            self.inner.push(&mut guard, val);
+        }
+        let mut woken = Woken::default();
+        woken.add(&guard.recv_wakers);
+        drop(guard);
+        self.inner.cond.notify_one();
+        woken.wake();
+        Ok(())
+    }
+
+    /// Number of messages sent but not recieved yet, including the ones a reciever already
+    /// moved into its local buffer.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Whether there are no messages waiting to be recieved.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The capacity of a bounded channel, `None` if unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.inner.cap
+    }
+
+    /// Number of senders still connected, including this one.
+    pub fn sender_count(&self) -> usize {
+        lock(&self.inner.mu).senders
+    }
+
+    /// Creates a [`WeakSender`], which doesn't keep the channel connected.
+    pub fn downgrade(&self) -> WeakSender<T> {
+        WeakSender {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+
+    /// A snapshot of the channel's counters, `None` unless enabled with [`Builder::stats`].
+    pub fn stats(&self) -> Option<ChannelStats> {
+        self.inner.stats.as_ref().map(Stats::snapshot)
+    }
+
+    /// A snapshot of how long the recieved messages waited in the channel, `None` unless
+    /// enabled with [`Builder::latency`].
+    #[cfg(feature = "std")]
+    pub fn latency(&self) -> Option<LatencyHistogram> {
+        self.inner.latency.as_ref().map(Latency::snapshot)
+    }
+
+    /// Whether the reciever is gone (or closed the channel), sending would fail.
+    pub fn is_closed(&self) -> bool {
+        lock(&self.inner.mu).done
+    }
+
+    /// Blocks untill the reciever is gone or closed the channel, so that an idle producer can
+    /// clean up without waiting for its next send to fail.
+    pub fn closed(&self) {
+        let mut guard = lock(&self.inner.mu);
+        while !guard.done {
+            guard = wait(&self.inner.closed, guard, None);
+        }
+    }
+
+    /// Sends a whole batch of values with a single lock acquisition and a single wake up of the
+    /// reciever, blocking like [`send`](Sender::send) whenever a bounded channel is full.
+    ///
+    /// The batch is collected before locking, so the iterator never runs under the channel's
+    /// mutex. On a rendezvous channel every value still needs its own hand-off.
+    ///
+    /// Fails with [`SendErr`] holding the values which weren't sent if the reciever is gone,
+    /// even for an empty batch.
+    pub fn send_all<I: IntoIterator<Item = T>>(&self, vals: I) -> Result<(), SendErr<Vec<T>>> {
+        let vals = vals.into_iter().collect::<Vec<_>>();
+        if vals.is_empty() {
+            // nothing to push which could fail, check like `send` would.
+            return match self.is_closed() {
+                true => Err(SendErr(vals)),
+                false => Ok(()),
+            };
+        }
+        let mut vals = vals.into_iter();
+        if let Some(queue) = &self.inner.queue {
+            return self.push_all(queue, vals);
+        }
+        if self.inner.cap == Some(0) {
+            while let Some(val) = vals.next() {
+                if let Err(SendErr(val)) = self.send(val) {
+                    return Err(SendErr(iter::once(val).chain(vals).collect()));
+                }
+            }
+            return Ok(());
+        }
+
+        let mut guard = lock(&self.inner.mu);
+        let mut pending = vals.next();
+        while let Some(val) = pending.take() {
+            if guard.done {
+                return Err(SendErr(iter::once(val).chain(vals).collect()));
+            }
+            if !self.inner.is_full(&guard) {
+                self.inner.push(&mut guard, val);
+                pending = vals.next();
+                continue;
+            }
+
+            // let the reciever make room with what we queued so far, then wait like `send`.
+            let mut woken = Woken::default();
+            woken.add(&guard.recv_wakers);
+            drop(guard);
+            self.inner.cond.notify_one();
+            woken.wake();
+            guard = lock(&self.inner.mu);
+            self.inner.blocked.fetch_add(1, Ordering::SeqCst);
+            // the reciever might have closed the channel while we were unlocked.
+            if !guard.done && self.inner.is_full(&guard) {
+                guard = self.inner.park(&self.inner.space, guard, None);
+            }
+            self.inner.blocked.fetch_sub(1, Ordering::SeqCst);
+            pending = Some(val);
+        }
+        let mut woken = Woken::default();
+        woken.add(&guard.recv_wakers);
+        drop(guard);
+        self.inner.cond.notify_one();
+        woken.wake();
+        Ok(())
+    }
+
+    // rendezvous send: place the value in the hand-off slot and wait for a reciever to take it.
+    fn hand_off<'a>(
+        &'a self,
+        mut guard: MutexGuard<'a, Critical<T>>,
+        val: T,
+        deadline: Option<Instant>,
+    ) -> Result<(), SendTimeoutError<T>> {
+        // another sender might be mid hand-off, wait for the slot to free up.
+        while !guard.done && guard.slot.is_some() {
+            if timed_out(deadline) {
+                return Err(SendTimeoutError::Timeout(val));
+            }
+            guard = self.inner.park(&self.inner.space, guard, deadline);
+        }
+        if guard.done {
+            return Err(SendTimeoutError::Disconnected(val));
+        }
+
+        self.inner.fill_slot(&mut guard, val);
+        let ticket = guard.taken;
+        self.inner.cond.notify_one();
+        let mut woken = Woken::default();
+        woken.add(&guard.recv_wakers);
+        drop(guard);
+        woken.wake();
+        guard = lock(&self.inner.mu);
+
+        while guard.taken == ticket {
+            // nobody took our value yet, so it is still in the slot.
+            if guard.done {
+                return Err(SendTimeoutError::Disconnected(guard.slot.take().unwrap()));
+            }
+            if timed_out(deadline) {
+                let val = guard.slot.take().unwrap();
+                let mut woken = Woken::default();
+                woken.add(&guard.send_wakers);
+                drop(guard);
+                // the slot is free again, let the next sender in.
+                self.inner.space.notify_all();
+                woken.wake();
+                return Err(SendTimeoutError::Timeout(val));
+            }
+            guard = self.inner.park(&self.inner.space, guard, deadline);
+        }
         Ok(())
     }
 }
 
 impl<T> Clone for Sender<T> {
     fn clone(&self) -> Self {
-        let mut guard = self.inner.mu.lock().unwrap();
+        let mut guard = lock(&self.inner.mu);
         guard.senders += 1;
         drop(guard);
 
@@ -54,64 +713,405 @@ impl<T> Clone for Sender<T> {
 
 impl<T> Drop for Sender<T> {
     fn drop(&mut self) {
-        let mut guard = self.inner.mu.lock().unwrap();
-        if guard.done {
-            // reciever exited.
-            return;
-        }
+        let mut guard = lock(&self.inner.mu);
         guard.senders -= 1;
-        if guard.senders == 0 {
+        // nothing to tell if the reciever exited already.
+        if guard.senders == 0 && !guard.done {
             guard.done = true;
+            let mut woken = Woken::default();
+            woken.add(&guard.recv_wakers);
             drop(guard);
-            self.inner.cond.notify_one(); // notify possibly hanging reciever.
+            self.inner.cond.notify_all(); // notify possibly hanging recievers.
+            woken.wake_in_drop();
+        }
+    }
+}
+
+/// A sender handle which doesn't count as a sender: the reciever sees the channel disconnect
+/// once all [`Sender`]s are gone, even while weak ones are still around.
+///
+/// Created by [`Sender::downgrade`], it has to be upgraded before sending.
+pub struct WeakSender<T> {
+    inner: Weak<Inner<T>>,
+}
+
+impl<T> WeakSender<T> {
+    /// Turns this back into a [`Sender`], `None` once all senders are gone (or the channel was
+    /// dropped altogether).
+    pub fn upgrade(&self) -> Option<Sender<T>> {
+        let inner = self.inner.upgrade()?;
+        let mut guard = lock(&inner.mu);
+        // a disconnected channel stays disconnected.
+        if guard.senders == 0 {
+            return None;
+        }
+        guard.senders += 1;
+        drop(guard);
+
+        Some(Sender { inner })
+    }
+}
+
+impl<T> Clone for WeakSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Weak::clone(&self.inner),
         }
     }
 }
 
+/// The recieving half of a channel. Can be cloned to spread the messages over several
+/// consumers, each message is recieved exactly once.
 pub struct Reciever<T> {
     inner: Arc<Inner<T>>,
     local_buf: VecDeque<T>,
+    // send times of the messages in `local_buf`, empty unless latency is tracked.
+    local_stamps: VecDeque<Instant>,
+    // our entry in `Critical::recv_wakers` while polled from async code.
+    waker: Option<usize>,
 }
 
 impl<T> Reciever<T> {
-    fn recv(&mut self) -> Option<T> {
+    /// Blocks untill a message is available and returns it.
+    ///
+    /// Fails with [`RecvError`] once the channel is empty and all senders are gone.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError)
+    }
+
+    /// Like [`recv`](Reciever::recv) but gives up with [`RecvTimeoutError::Timeout`] once
+    /// `timeout` elapsed.
+    #[cfg(feature = "std")]
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_until(Instant::now().checked_add(timeout))
+    }
+
+    /// Like [`recv_timeout`](Reciever::recv_timeout) but waits untill an absolute `deadline`.
+    #[cfg(feature = "std")]
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.recv_until(Some(deadline))
+    }
+
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
         // try to consume local copy of buffer first to reduce mutex contention.
-        if let Some(v) = self.local_buf.pop_back() {
-            return Some(v);
+        if let Some(v) = self.pop_local() {
+            return Ok(v);
+        }
+        if self.inner.queue.is_some() {
+            if let Some(v) = self.spin_pop() {
+                return Ok(v);
+            }
         }
 
-        // go in a cycle of acquiring mutex -> check if we have any work to do -> go back to sleep
-        // (loop also mostly accounts for spureous wake ups)
+        // go in a cycle of checking if we have any work to do -> go back to sleep -> re-acquire
+        // the mutex on wake up (loop also mostly accounts for spureous wake ups)
+        let mut guard = lock(&self.inner.mu);
+        let mut parked = false;
         loop {
-            let mut guard = self.inner.mu.lock().unwrap();
-            match guard.buf.pop_back() {
-                // message on queue, recieve it and return it.
-                Some(v) => {
-                    // swap our local (empty) buffer (which has an allocated capacity) with the
-                    // incoming buffer.
-                    //
-                    // this will keep some data local taking advantage that we only have one
-                    // reciever, this data we can acess without interacting with the mutex.
-                    mem::swap(&mut self.local_buf, &mut guard.buf);
-                    return Some(v);
+            // read before taking, a lock-free sender might push its last message and disconnect
+            // right after we found the queue empty.
+            let done = guard.done;
+            let mut woken = Woken::default();
+            if let Some(v) = self.inner.take(
+                &mut guard,
+                &mut self.local_buf,
+                &mut self.local_stamps,
+                &mut woken,
+            ) {
+                drop(guard);
+                woken.wake();
+                return Ok(v);
+            }
+            // we got woken up because all workers got dropped.
+            if done {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            if timed_out(deadline) {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            if let Some(queue) = &self.inner.queue {
+                // lock-free senders only take the mutex to wake us once they see us in
+                // `sleeping`, so re-check the queue after announcing ourselves.
+                self.inner.sleeping.fetch_add(1, Ordering::SeqCst);
+                if let Some(v) = queue.pop() {
+                    self.inner.sleeping.fetch_sub(1, Ordering::SeqCst);
+                    self.inner.popped(&mut guard, &mut woken);
+                    drop(guard);
+                    woken.wake();
+                    return Ok(v);
                 }
-                // we got woken up because all workers got dropped.
-                None if guard.done => return None,
-                // spureous wakeup or first call to an empty buffer. Anyways we go back to sleep
-                // untill something "interesting" happens (one of the above).
-                None => {
-                    self.inner.cond.wait(guard);
+            }
+            // spureous wakeup or first call to an empty buffer. Anyways we go back to sleep
+            // untill something "interesting" happens (one of the above).
+            if self.inner.cap == Some(0) {
+                // a parked reciever makes a rendezvous channel ready for sending. The woken
+                // senders need the mutex to look, so they only see us once we're counted and
+                // parked. That's also why this is the one place waking under the lock, nothing
+                // is left to notify and `waiting` isn't bumped yet if a waker panics.
+                woken.add(&guard.send_wakers);
+                woken.wake();
+            }
+            guard.waiting += 1;
+            // only counted once we actually park, the re-check above may still find a message.
+            if let Some(stats) = &self.inner.stats {
+                if parked {
+                    stats.spurious_wakeup();
                 }
+                stats.parked();
+            }
+            guard = self.inner.park(&self.inner.cond, guard, deadline);
+            parked = true;
+            guard.waiting -= 1;
+            if self.inner.queue.is_some() {
+                self.inner.sleeping.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Attempts to recieve a message without blocking.
+    ///
+    /// Fails with [`TryRecvError::Empty`] if no message is ready and with
+    /// [`TryRecvError::Disconnected`] once the channel is empty and all senders are gone.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if let Some(v) = self.pop_local() {
+            return Ok(v);
+        }
+
+        let mut guard = lock(&self.inner.mu);
+        let done = guard.done;
+        let mut woken = Woken::default();
+        let v = self.inner.take(
+            &mut guard,
+            &mut self.local_buf,
+            &mut self.local_stamps,
+            &mut woken,
+        );
+        drop(guard);
+        woken.wake();
+        match v {
+            Some(v) => Ok(v),
+            None if done => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Returns an iterator which blocks waiting for messages, ending once the channel is empty
+    /// and every sender is gone.
+    pub fn iter(&mut self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Returns an iterator over the messages which are ready right now, including the ones
+    /// already in the local buffer. Never blocks.
+    pub fn try_iter(&mut self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+
+    // takes a message without locking: off the local buffer, or straight off a lock-free queue.
+    fn pop_local(&mut self) -> Option<T> {
+        if let Some(queue) = &self.inner.queue {
+            let v = queue.pop()?;
+            self.inner.record_received(1);
+            self.inner.wake_blocked();
+            return Some(v);
+        }
+
+        let v = self.local_buf.pop_back()?;
+        self.inner.record_received(1);
+        self.inner.record_latency(self.local_stamps.pop_back());
+        self.inner.release_held(1);
+        Some(v)
+    }
+
+    /// Number of messages sent but not recieved yet, including the ones in the local buffer.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Whether there are no messages waiting to be recieved.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The capacity of a bounded channel, `None` if unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.inner.cap
+    }
+
+    /// Number of senders still connected.
+    pub fn sender_count(&self) -> usize {
+        lock(&self.inner.mu).senders
+    }
+
+    /// A snapshot of the channel's counters, `None` unless enabled with [`Builder::stats`].
+    pub fn stats(&self) -> Option<ChannelStats> {
+        self.inner.stats.as_ref().map(Stats::snapshot)
+    }
+
+    /// A snapshot of how long the recieved messages waited in the channel, `None` unless
+    /// enabled with [`Builder::latency`].
+    #[cfg(feature = "std")]
+    pub fn latency(&self) -> Option<LatencyHistogram> {
+        self.inner.latency.as_ref().map(Latency::snapshot)
+    }
+
+    /// Closes the channel without dropping the reciever, for a graceful shutdown.
+    ///
+    /// Sends fail with [`SendErr`] from now on (blocked senders included), but every message
+    /// already queued, including the ones in the local buffer, can still be recieved. Once they
+    /// are drained `recv` fails with [`RecvError`]. Closing affects all clones of the reciever.
+    pub fn close(&self) {
+        let guard = lock(&self.inner.mu);
+        self.inner.close(guard).wake();
+    }
+
+    /// Blocks untill a message is available, then moves up to `max` messages into `buf` and
+    /// returns how many were moved.
+    ///
+    /// Messages already in the local buffer are handed out first without locking, otherwise the
+    /// batch is taken with a single lock acquisition (on an mpmc channel only this reciever's
+    /// share of the backlog). Returns `Ok(0)` right away if `max` is 0.
+    ///
+    /// Fails with [`RecvError`] once the channel is empty and all senders are gone.
+    pub fn recv_many(&mut self, buf: &mut Vec<T>, max: usize) -> Result<usize, RecvError> {
+        if max == 0 {
+            return Ok(0);
+        }
+
+        let n = self.drain_local(buf, max);
+        if n > 0 {
+            return Ok(n);
+        }
+        // `recv` refills the local buffer while taking the first message.
+        buf.push(self.recv()?);
+        Ok(1 + self.drain_local(buf, max - 1))
+    }
+
+    // moves up to `max` messages into `buf` without locking, see `pop_local`.
+    fn drain_local(&mut self, buf: &mut Vec<T>, max: usize) -> usize {
+        if let Some(queue) = &self.inner.queue {
+            let start = buf.len();
+            buf.extend(iter::from_fn(|| queue.pop()).take(max));
+            if buf.len() > start {
+                self.inner.record_received(buf.len() - start);
+                self.inner.wake_blocked();
             }
+            return buf.len() - start;
+        }
+
+        // the oldest messages sit at the back.
+        let n = max.min(self.local_buf.len());
+        let at = self.local_buf.len() - n;
+        buf.extend(self.local_buf.drain(at..).rev());
+        self.inner.record_received(n);
+        if self.inner.latency.is_some() {
+            for stamp in self.local_stamps.drain(at..) {
+                self.inner.record_latency(Some(stamp));
+            }
+        }
+        self.inner.release_held(n);
+        n
+    }
+}
+
+/// Blocking iterator over a [`Reciever`], created by [`Reciever::iter`].
+pub struct Iter<'a, T> {
+    rx: &'a mut Reciever<T>,
+}
+
+impl<T> Iterator for Iter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+/// Non-blocking iterator over a [`Reciever`], created by [`Reciever::try_iter`].
+pub struct TryIter<'a, T> {
+    rx: &'a mut Reciever<T>,
+}
+
+impl<T> Iterator for TryIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+}
+
+/// Owning blocking iterator over a [`Reciever`].
+pub struct IntoIter<T> {
+    rx: Reciever<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<T> IntoIterator for Reciever<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { rx: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Reciever<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> Clone for Reciever<T> {
+    /// Creates another reciever for the same channel. Every message is still delivered to
+    /// exactly one of the recievers.
+    fn clone(&self) -> Self {
+        let mut guard = lock(&self.inner.mu);
+        guard.receivers += 1;
+        drop(guard);
+
+        Self {
+            inner: Arc::clone(&self.inner),
+            local_buf: VecDeque::default(),
+            local_stamps: VecDeque::default(),
+            waker: None,
         }
     }
 }
 
 impl<T> Drop for Reciever<T> {
     fn drop(&mut self) {
-        // set done to true to stop senders from blocking.
-        let mut guard = self.inner.mu.lock().unwrap();
-        guard.done = true;
+        let mut guard = lock(&self.inner.mu);
+        self.inner.unwatch_recv(&mut guard, &mut self.waker);
+        self.inner
+            .held
+            .fetch_sub(self.local_buf.len(), Ordering::SeqCst);
+        guard.receivers -= 1;
+        if guard.receivers > 0 {
+            if !self.local_buf.is_empty() {
+                // hand our local buffer back to the remaining recievers. These are older than
+                // anything in the shared buffer so they go to the back, next in line.
+                guard.buf.extend(self.local_buf.drain(..));
+                guard.stamps.extend(self.local_stamps.drain(..));
+                let mut woken = Woken::default();
+                woken.add(&guard.recv_wakers);
+                drop(guard);
+                self.inner.cond.notify_all();
+                woken.wake_in_drop();
+            }
+            return;
+        }
+
+        self.inner.close(guard).wake_in_drop();
     }
 }
 
@@ -122,18 +1122,34 @@ pub fn unbounded<T>() -> (Sender<T>, Reciever<T>) {
     //      - When a write occurs.
     //      - When a Sender / Reciever gets dropped.
 
-    let inner = Inner {
-        mu: Mutex::new(Critical {
-            buf: VecDeque::default(),
-            senders: 1,
-            done: false,
-        }),
-        cond: Condvar::default(),
-    };
+    channel(Inner::new(None, None))
+}
+
+/// Creates a channel which holds at most `cap` messages. Once full, `send` blocks untill the
+/// reciever consumes a message.
+///
+/// Messages the reciever already took into its local buffer keep counting against `cap` untill
+/// they are returned by `recv`.
+///
+/// A `cap` of zero creates a [`rendezvous`] channel. See [`lock_free::bounded`] for a flavour
+/// which never allocates per message.
+pub fn bounded<T>(cap: usize) -> (Sender<T>, Reciever<T>) {
+    channel(Inner::new(Some(cap), None))
+}
+
+/// Creates a zero-capacity channel: `send` only returns once a reciever has taken the value,
+/// making every message a synchronization point between the two threads.
+pub fn rendezvous<T>() -> (Sender<T>, Reciever<T>) {
+    bounded(0)
+}
+
+fn channel<T>(inner: Inner<T>) -> (Sender<T>, Reciever<T>) {
     let inner = Arc::new(inner);
     let rx = Reciever {
         inner: Arc::clone(&inner),
         local_buf: VecDeque::default(),
+        local_stamps: VecDeque::default(),
+        waker: None,
     };
     let tx = Sender {
         inner: Arc::clone(&inner),
@@ -160,14 +1176,14 @@ fn test_interface() {
 fn ping_pong() {
     let (mut tx, mut rx) = unbounded();
     tx.send(42);
-    assert_eq!(rx.recv(), Some(42));
+    assert_eq!(rx.recv(), Ok(42));
 }
 
 #[test]
 fn closed_tx() {
     let (tx, mut rx) = unbounded::<()>();
     drop(tx);
-    assert_eq!(rx.recv(), None);
+    assert_eq!(rx.recv(), Err(RecvError));
 }
 
 #[test]
@@ -177,6 +1193,668 @@ fn closed_rx() {
     tx.send(42);
 }
 
+#[test]
+fn bounded_ping_pong() {
+    let (tx, mut rx) = bounded(1);
+    for i in 0..10 {
+        tx.send(i);
+        assert_eq!(rx.recv(), Ok(i));
+    }
+}
+
+#[test]
+fn bounded_blocks_when_full() {
+    let (tx, mut rx) = bounded(2);
+    let sent = Arc::new(AtomicUsize::new(0));
+    let handle = thread::spawn({
+        let sent = Arc::clone(&sent);
+        move || {
+            for i in 0..4 {
+                tx.send(i).unwrap();
+                sent.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(sent.load(Ordering::SeqCst), 2);
+
+    // takes 0 and swaps 1 into the local buffer, which still occupies a slot.
+    assert_eq!(rx.recv(), Ok(0));
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(sent.load(Ordering::SeqCst), 3);
+
+    // consuming from the local buffer frees the last slot.
+    assert_eq!(rx.recv(), Ok(1));
+    handle.join().unwrap();
+    assert_eq!(rx.recv(), Ok(2));
+    assert_eq!(rx.recv(), Ok(3));
+    assert_eq!(rx.recv(), Err(RecvError));
+}
+
+#[test]
+fn bounded_closed_rx_unblocks() {
+    let (tx, rx) = bounded(1);
+    tx.send(1).unwrap();
+    let handle = thread::spawn(move || tx.send(2));
+    thread::sleep(Duration::from_millis(50));
+    drop(rx);
+    assert_eq!(handle.join().unwrap(), Err(SendErr(2)));
+}
+
+#[test]
+fn bounded_many() {
+    let (tx, mut rx) = bounded(4);
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    tx.send(i).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut n = 0;
+    while rx.recv().is_ok() {
+        n += 1;
+    }
+    assert_eq!(n, 4000);
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn rendezvous_waits_for_taker() {
+    let (tx, mut rx) = rendezvous();
+    let sent = Arc::new(AtomicUsize::new(0));
+    let handle = thread::spawn({
+        let sent = Arc::clone(&sent);
+        move || {
+            tx.send(42).unwrap();
+            sent.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(sent.load(Ordering::SeqCst), 0);
+    assert_eq!(rx.recv(), Ok(42));
+    handle.join().unwrap();
+    assert_eq!(sent.load(Ordering::SeqCst), 1);
+    assert_eq!(rx.recv(), Err(RecvError));
+}
+
+#[test]
+fn rendezvous_closed_rx_returns_value() {
+    let (tx, rx) = bounded(0);
+    let handle = thread::spawn(move || tx.send(7));
+    thread::sleep(Duration::from_millis(50));
+    drop(rx);
+    assert_eq!(handle.join().unwrap(), Err(SendErr(7)));
+}
+
+#[test]
+fn rendezvous_many_senders() {
+    let (tx, mut rx) = rendezvous();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..100 {
+                    tx.send(i).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut sum = 0;
+    while let Ok(v) = rx.recv() {
+        sum += v;
+    }
+    assert_eq!(sum, 4 * (0..100).sum::<i32>());
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn try_recv_states() {
+    let (tx, mut rx) = unbounded();
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    // the first try_recv swaps 2 into the local buffer, the second one drains it.
+    assert_eq!(rx.try_recv(), Ok(1));
+    tx.send(3).unwrap();
+    assert_eq!(rx.try_recv(), Ok(2));
+    drop(tx);
+    assert_eq!(rx.try_recv(), Ok(3));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+}
+
+#[test]
+fn try_send_states() {
+    let (tx, mut rx) = bounded(1);
+    assert_eq!(tx.try_send(1), Ok(()));
+    assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(tx.try_send(3), Ok(()));
+    drop(rx);
+    assert_eq!(tx.try_send(4), Err(TrySendError::Disconnected(4)));
+}
+
+#[test]
+fn try_send_counts_local_buf() {
+    let (tx, mut rx) = bounded(2);
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    // 2 moves into the local buffer and keeps its slot.
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(tx.try_send(3), Ok(()));
+    assert_eq!(tx.try_send(4), Err(TrySendError::Full(4)));
+    assert_eq!(rx.try_recv(), Ok(2));
+    assert_eq!(tx.try_send(4), Ok(()));
+}
+
+#[test]
+fn rendezvous_try_send_needs_waiting_reciever() {
+    let (tx, mut rx) = rendezvous();
+    assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+    let handle = thread::spawn(move || rx.recv());
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(tx.try_send(2), Ok(()));
+    assert_eq!(handle.join().unwrap(), Ok(2));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn recv_timeout_states() {
+    let (tx, mut rx) = unbounded();
+    let start = Instant::now();
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(50)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    tx.send(1).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_millis(50)), Ok(1));
+    drop(tx);
+    assert_eq!(
+        rx.recv_deadline(Instant::now() + Duration::from_secs(10)),
+        Err(RecvTimeoutError::Disconnected)
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn recv_timeout_wakes_on_send() {
+    let (tx, mut rx) = unbounded();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        tx.send(1).unwrap();
+    });
+    assert_eq!(rx.recv_timeout(Duration::from_secs(10)), Ok(1));
+    handle.join().unwrap();
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn send_timeout_on_full_channel() {
+    let (tx, mut rx) = bounded(1);
+    tx.send(1).unwrap();
+    assert_eq!(
+        tx.send_timeout(2, Duration::from_millis(20)),
+        Err(SendTimeoutError::Timeout(2))
+    );
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(tx.send_timeout(3, Duration::from_millis(20)), Ok(()));
+    drop(rx);
+    assert_eq!(
+        tx.send_timeout(4, Duration::from_millis(20)),
+        Err(SendTimeoutError::Disconnected(4))
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn rendezvous_send_timeout_reclaims_value() {
+    let (tx, mut rx) = rendezvous();
+    assert_eq!(
+        tx.send_deadline(1, Instant::now() + Duration::from_millis(20)),
+        Err(SendTimeoutError::Timeout(1))
+    );
+    // the value didn't stay behind in the hand-off slot.
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn iter_until_disconnect() {
+    let (tx, rx) = unbounded();
+    let handle = thread::spawn(move || {
+        for i in 0..10 {
+            tx.send(i).unwrap();
+        }
+    });
+
+    let mut got = Vec::new();
+    for v in rx {
+        got.push(v);
+    }
+    handle.join().unwrap();
+    assert_eq!(got, (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn try_iter_drains_ready() {
+    let (tx, mut rx) = unbounded();
+    for i in 0..3 {
+        tx.send(i).unwrap();
+    }
+    // pull 1 and 2 into the local buffer first.
+    assert_eq!(rx.recv(), Ok(0));
+    tx.send(3).unwrap();
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(rx.try_iter().next(), None);
+
+    tx.send(4).unwrap();
+    drop(tx);
+    assert_eq!((&mut rx).into_iter().collect::<Vec<_>>(), [4]);
+}
+
+#[test]
+fn mpmc_delivers_once() {
+    let (tx, rx) = unbounded();
+    let consumers: Vec<_> = (0..4)
+        .map(|_| {
+            let rx = rx.clone();
+            thread::spawn(move || rx.into_iter().collect::<Vec<usize>>())
+        })
+        .collect();
+    drop(rx);
+
+    let producers: Vec<_> = (0..4)
+        .map(|p| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    tx.send(p * 1000 + i).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+    for p in producers {
+        p.join().unwrap();
+    }
+
+    let mut got: Vec<_> = consumers
+        .into_iter()
+        .flat_map(|c| c.join().unwrap())
+        .collect();
+    got.sort_unstable();
+    assert_eq!(got, (0..4000).collect::<Vec<_>>());
+}
+
+#[test]
+fn mpmc_takes_fair_share() {
+    let (tx, mut rx1) = unbounded();
+    let mut rx2 = rx1.clone();
+    for i in 0..9 {
+        tx.send(i).unwrap();
+    }
+
+    // rx1 takes 0 plus half of the remaining backlog, the rest stays reachable for rx2.
+    assert_eq!(rx1.recv(), Ok(0));
+    assert_eq!(rx2.try_recv(), Ok(5));
+    assert_eq!(rx1.try_iter().collect::<Vec<_>>(), [1, 2, 3, 4, 7, 8]);
+    assert_eq!(rx2.try_recv(), Ok(6));
+}
+
+#[test]
+fn mpmc_drop_returns_local_buf() {
+    let (tx, mut rx1) = bounded(4);
+    let mut rx2 = rx1.clone();
+    for i in 0..4 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(rx1.recv(), Ok(0));
+    drop(rx1);
+    assert_eq!(rx2.try_iter().collect::<Vec<_>>(), [1, 2, 3]);
+    // the returned messages no longer count against the capacity twice.
+    for i in 0..4 {
+        tx.try_send(i).unwrap();
+    }
+}
+
+#[test]
+fn mpmc_disconnect_wakes_all() {
+    let (tx, rx) = unbounded::<()>();
+    let handles: Vec<_> = (0..3)
+        .map(|_| {
+            let mut rx = rx.clone();
+            thread::spawn(move || rx.recv())
+        })
+        .collect();
+    thread::sleep(Duration::from_millis(50));
+    drop(tx);
+    for h in handles {
+        assert_eq!(h.join().unwrap(), Err(RecvError));
+    }
+
+    // the channel stays open while a single reciever clone is alive.
+    let (tx, rx1) = unbounded();
+    let rx2 = rx1.clone();
+    drop(rx1);
+    assert_eq!(tx.send(1), Ok(()));
+    drop(rx2);
+    assert_eq!(tx.send(2), Err(SendErr(2)));
+}
+
+#[test]
+fn recv_many_batches() {
+    let (tx, mut rx) = unbounded();
+    let mut buf = Vec::new();
+    for i in 0..10 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(rx.recv_many(&mut buf, 4), Ok(4));
+    assert_eq!(rx.recv_many(&mut buf, 0), Ok(0));
+    // a send after the local buffer got filled comes after what's left in there.
+    tx.send(10).unwrap();
+    assert_eq!(rx.recv_many(&mut buf, 100), Ok(6));
+    assert_eq!(rx.recv_many(&mut buf, 100), Ok(1));
+    assert_eq!(buf, (0..11).collect::<Vec<_>>());
+
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.send(11).unwrap();
+    });
+    assert_eq!(rx.recv_many(&mut buf, 100), Ok(1));
+    handle.join().unwrap();
+    assert_eq!(rx.recv_many(&mut buf, 100), Err(RecvError));
+    assert_eq!(buf.last(), Some(&11));
+}
+
+#[test]
+fn recv_many_frees_capacity() {
+    let (tx, mut rx) = bounded(4);
+    let handle = thread::spawn(move || {
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+    });
+    let mut buf = Vec::new();
+    while rx.recv_many(&mut buf, 3).is_ok() {}
+    handle.join().unwrap();
+    assert_eq!(buf, (0..100).collect::<Vec<_>>());
+
+    let (tx, mut rx) = lock_free::bounded(4);
+    let handle = thread::spawn(move || {
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+    });
+    let mut buf = Vec::new();
+    while rx.recv_many(&mut buf, 3).is_ok() {}
+    handle.join().unwrap();
+    assert_eq!(buf, (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn send_all_batches() {
+    let (tx, mut rx) = unbounded();
+    tx.send_all(0..5).unwrap();
+    tx.send_all(Vec::new()).unwrap();
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+
+    // a batch larger than the capacity waits for room in between.
+    let (tx, mut rx) = bounded(3);
+    let handle = thread::spawn(move || tx.send_all(0..100));
+    assert_eq!(rx.iter().collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+    assert_eq!(handle.join().unwrap(), Ok(()));
+
+    let (tx, mut rx) = lock_free::bounded(3);
+    let handle = thread::spawn(move || tx.send_all(0..100));
+    assert_eq!(rx.iter().collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+    assert_eq!(handle.join().unwrap(), Ok(()));
+}
+
+#[test]
+fn send_all_empty_batch_checks_closed() {
+    for (tx, rx) in [
+        unbounded(),
+        bounded(4),
+        rendezvous(),
+        lock_free::unbounded(),
+        lock_free::bounded(4),
+    ] {
+        assert_eq!(tx.send_all([]), Ok(()));
+        rx.close();
+        assert_eq!(tx.send_all([]), Err(SendErr(Vec::new())));
+        assert_eq!(tx.send_all([1]), Err(SendErr(vec![1])));
+    }
+}
+
+#[test]
+fn send_all_returns_unsent() {
+    let (tx, rx) = unbounded();
+    drop(rx);
+    assert_eq!(tx.send_all([1, 2, 3]), Err(SendErr(vec![1, 2, 3])));
+
+    let (tx, rx) = lock_free::unbounded();
+    drop(rx);
+    assert_eq!(tx.send_all([1, 2, 3]), Err(SendErr(vec![1, 2, 3])));
+
+    // the reciever goes away while we wait for room.
+    let (tx, mut rx) = bounded(2);
+    let handle = thread::spawn(move || tx.send_all(0..5));
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(rx.recv(), Ok(0));
+    thread::sleep(Duration::from_millis(20));
+    drop(rx);
+    assert_eq!(handle.join().unwrap(), Err(SendErr(vec![3, 4])));
+}
+
+#[test]
+fn close_drains_queued() {
+    let (tx, mut rx) = unbounded();
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    // moves both into the local buffer.
+    assert_eq!(rx.recv(), Ok(1));
+    tx.send(3).unwrap();
+    rx.close();
+    assert_eq!(tx.send(4), Err(SendErr(4)));
+    assert_eq!(rx.iter().collect::<Vec<_>>(), [2, 3]);
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+    let (tx, mut rx) = lock_free::unbounded();
+    tx.send(1).unwrap();
+    rx.close();
+    assert_eq!(tx.try_send(2), Err(TrySendError::Disconnected(2)));
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.recv(), Err(RecvError));
+}
+
+#[test]
+fn close_wakes_blocked() {
+    let (tx, rx) = bounded(1);
+    tx.send(1).unwrap();
+    let handle = thread::spawn(move || tx.send(2));
+    thread::sleep(Duration::from_millis(50));
+    rx.close();
+    assert_eq!(handle.join().unwrap(), Err(SendErr(2)));
+
+    // a reciever sees the close although a sender is still alive.
+    let (tx, rx) = unbounded::<()>();
+    let mut rx2 = rx.clone();
+    let handle = thread::spawn(move || rx2.recv());
+    thread::sleep(Duration::from_millis(50));
+    rx.close();
+    assert_eq!(handle.join().unwrap(), Err(RecvError));
+}
+
+#[test]
+fn sender_closed_detection() {
+    let (tx, rx) = unbounded::<()>();
+    assert!(!tx.is_closed());
+    let tx2 = tx.clone();
+    let handle = thread::spawn(move || tx2.closed());
+    thread::sleep(Duration::from_millis(20));
+    drop(rx);
+    handle.join().unwrap();
+    assert!(tx.is_closed());
+
+    let (tx, rx) = lock_free::bounded::<()>(1);
+    rx.close();
+    assert!(tx.is_closed());
+    tx.closed();
+}
+
+#[test]
+fn introspection_counts_local_buf() {
+    let (tx, mut rx) = unbounded();
+    assert!(tx.is_empty());
+    assert_eq!(tx.capacity(), None);
+    tx.send_all(0..5).unwrap();
+    assert_eq!(rx.recv(), Ok(0));
+    // the other four sit in the local buffer now.
+    assert_eq!(tx.len(), 4);
+    tx.send(5).unwrap();
+    assert_eq!(rx.len(), 5);
+    drop(rx);
+    assert_eq!(tx.len(), 1);
+
+    let (tx, mut rx1) = bounded(8);
+    let rx2 = rx1.clone();
+    assert_eq!(rx1.capacity(), Some(8));
+    tx.send_all(0..4).unwrap();
+    assert_eq!(rx1.recv(), Ok(0));
+    drop(rx2);
+    assert_eq!(tx.len(), 3);
+    assert_eq!(rendezvous::<()>().0.capacity(), Some(0));
+
+    let (tx, rx) = lock_free::bounded(4);
+    tx.send(1).unwrap();
+    assert_eq!((rx.len(), rx.capacity()), (1, Some(4)));
+}
+
+#[test]
+fn sender_count_tracks_clones() {
+    let (tx, rx) = unbounded::<()>();
+    let tx2 = tx.clone();
+    assert_eq!(rx.sender_count(), 2);
+    rx.close();
+    drop(tx2);
+    assert_eq!(tx.sender_count(), 1);
+}
+
+#[test]
+fn weak_sender_doesnt_keep_channel_alive() {
+    let (tx, mut rx) = unbounded();
+    let weak = tx.downgrade();
+    assert_eq!(rx.sender_count(), 1);
+
+    let tx2 = weak.upgrade().unwrap();
+    assert_eq!(rx.sender_count(), 2);
+    tx2.send(1).unwrap();
+    drop(tx2);
+    drop(tx);
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.recv(), Err(RecvError));
+    assert!(weak.upgrade().is_none());
+
+    drop(rx);
+    assert!(weak.clone().upgrade().is_none());
+}
+
+// a waker which panics when woken, like a buggy executor's.
+#[cfg(test)]
+struct Panicking;
+
+#[cfg(test)]
+impl std::task::Wake for Panicking {
+    fn wake(self: Arc<Self>) {
+        panic!("waker panicked");
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn survives_poisoned_lock() {
+    let (tx, mut rx) = rendezvous();
+    let waker = Waker::from(Arc::new(Panicking));
+    let mut id = None;
+    tx.inner
+        .watch_send(&mut lock(&tx.inner.mu), &mut id, &waker);
+    // parking a reciever wakes the registered sender while holding the lock, the one place
+    // which does. The clone is dropped while unwinding.
+    let mut rx2 = rx.clone();
+    assert!(thread::spawn(move || rx2.recv()).join().is_err());
+    assert!(rx.inner.mu.is_poisoned());
+    tx.inner.unwatch_send(&mut lock(&tx.inner.mu), &mut id);
+
+    assert_eq!(rx.sender_count(), 1);
+    let handle = thread::spawn(move || {
+        tx.send(1).unwrap();
+        drop(tx);
+    });
+    assert_eq!(rx.recv(), Ok(1));
+    handle.join().unwrap();
+    assert_eq!(rx.recv(), Err(RecvError));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn panicking_waker_doesnt_stall_blocked_recievers() {
+    let (tx, rx) = unbounded();
+    let mut async_rx = rx.clone();
+    let waker = Waker::from(Arc::new(Panicking));
+    assert!(async_rx
+        .poll_recv(&mut std::task::Context::from_waker(&waker))
+        .is_pending());
+
+    let mut sync_rx = rx;
+    let handle = thread::spawn(move || {
+        let start = Instant::now();
+        let res = sync_rx.recv_timeout(Duration::from_secs(3));
+        (res, start.elapsed())
+    });
+    thread::sleep(Duration::from_millis(50));
+    // the message is queued and the blocked reciever notified before the async one's waker
+    // gets to panic.
+    let sent = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tx.send(1)));
+    assert!(sent.is_err());
+    let (res, waited) = handle.join().unwrap();
+    assert_eq!(res, Ok(1));
+    assert!(waited < Duration::from_secs(1));
+
+    // the last sender wakes the async reciever too, its panic must not escape `drop`.
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(tx))).is_ok());
+    assert!(!async_rx.inner.mu.is_poisoned());
+}
+
+#[test]
+fn panicking_waker_doesnt_count_a_taker() {
+    let (tx, rx) = bounded::<i32>(0);
+    let waker = Waker::from(Arc::new(Panicking));
+    let mut id = None;
+    tx.inner
+        .watch_send(&mut lock(&tx.inner.mu), &mut id, &waker);
+    // parking wakes the registered sender, which panics before the reciever would wait.
+    let mut rx2 = rx.clone();
+    assert!(thread::spawn(move || rx2.recv()).join().is_err());
+    tx.inner.unwatch_send(&mut lock(&tx.inner.mu), &mut id);
+
+    // nobody is waiting, so there is still no taker for the value.
+    assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+}
+
 // uncomment if you want to check for blocking behaviour.
 // #[test]
 // fn test_blocking() {
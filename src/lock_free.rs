@@ -0,0 +1,384 @@
+//! Lock-free backends for [`Sender`] and [`Reciever`].
+//!
+//! The channels created here keep their messages in a lock-free queue instead of the
+//! mutex-guarded `VecDeque` used by [`crate::unbounded`] and [`crate::bounded`]. Neither side
+//! touches the channel's mutex on the fast path, it is only taken to park a reciever on an empty
+//! channel (or a sender on a full one) and to wake it up again. There is no local buffer on the
+//! recieving side either, every `recv` pops straight off the shared queue.
+
+mod array;
+mod list;
+
+use alloc::vec::Vec;
+use core::{
+    cell::Cell,
+    hint, iter,
+    ops::{Deref, DerefMut},
+    sync::atomic::Ordering,
+};
+
+use crate::{lock, sync::Instant, timed_out, Inner, Reciever, SendErr, SendTimeoutError, Sender};
+
+#[cfg(test)]
+use std::thread;
+
+pub(crate) use array::Array;
+pub(crate) use list::List;
+
+// the queue a lock-free channel keeps its messages in, instead of `Critical::buf`.
+pub(crate) enum Queue<T> {
+    List(List<T>),
+    Array(Array<T>),
+}
+
+// why a push handed the value back.
+pub(crate) enum PushError<T> {
+    Full(T),
+    Closed(T),
+}
+
+impl<T> Queue<T> {
+    pub(crate) fn push(&self, val: T) -> Result<(), PushError<T>> {
+        match self {
+            Self::List(q) => q.push(val).map_err(PushError::Closed),
+            Self::Array(q) => q.push(val),
+        }
+    }
+
+    pub(crate) fn pop(&self) -> Option<T> {
+        match self {
+            Self::List(q) => q.pop(),
+            Self::Array(q) => q.pop(),
+        }
+    }
+
+    // makes every later push fail, returns whether this call closed the queue.
+    pub(crate) fn close(&self) -> bool {
+        match self {
+            Self::List(q) => q.close(),
+            Self::Array(q) => q.close(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Self::List(q) => q.len(),
+            Self::Array(q) => q.len(),
+        }
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        match self {
+            Self::List(_) => false,
+            Self::Array(q) => q.is_full(),
+        }
+    }
+}
+
+/// Creates an unbounded channel backed by a lock-free linked list of blocks.
+///
+/// Scales better than [`crate::unbounded`] with many concurrent senders, at the cost of
+/// allocating a new block every 31 messages.
+pub fn unbounded<T>() -> (Sender<T>, Reciever<T>) {
+    crate::channel(Inner::new(None, Some(Queue::List(List::new()))))
+}
+
+/// Creates a bounded channel backed by a ring buffer of `cap` slots, allocated once up front.
+///
+/// Like [`crate::bounded`] senders block while `cap` messages are in flight, but sending never
+/// allocates. A `cap` of 0 has no room for a ring and gives a [`crate::rendezvous`] channel.
+pub fn bounded<T>(cap: usize) -> (Sender<T>, Reciever<T>) {
+    if cap == 0 {
+        return crate::rendezvous();
+    }
+    crate::channel(Inner::new(Some(cap), Some(Queue::Array(Array::new(cap)))))
+}
+
+impl<T> Reciever<T> {
+    // messages tend to arrive in bursts, spin for a bit on an empty queue before parking.
+    pub(crate) fn spin_pop(&mut self) -> Option<T> {
+        let backoff = Backoff::new();
+        while !backoff.is_completed() {
+            if let Some(v) = self.pop_local() {
+                return Some(v);
+            }
+            backoff.snooze();
+        }
+        None
+    }
+}
+
+impl<T> Sender<T> {
+    // `send_all` of a lock-free channel, only wakes the reciever once at the end.
+    pub(crate) fn push_all(
+        &self,
+        queue: &Queue<T>,
+        mut vals: impl Iterator<Item = T>,
+    ) -> Result<(), SendErr<Vec<T>>> {
+        let mut res = Ok(());
+        while let Some(val) = vals.next() {
+            let val = match queue.push(val) {
+                Ok(()) => {
+                    self.inner.record_sent(1, || queue.len());
+                    continue;
+                }
+                Err(PushError::Closed(v)) => v,
+                Err(PushError::Full(v)) => {
+                    // wake the reciever for what we pushed so far, and wait for room.
+                    self.inner.wake_sleepers();
+                    match self.push_until(queue, v, None) {
+                        Ok(()) => continue,
+                        Err(err) => err.into_inner(),
+                    }
+                }
+            };
+            res = Err(SendErr(iter::once(val).chain(vals).collect()));
+            break;
+        }
+        self.inner.wake_sleepers();
+        res
+    }
+
+    // the blocking send of a lock-free channel, parks on `space` while the queue is full.
+    pub(crate) fn push_until(
+        &self,
+        queue: &Queue<T>,
+        val: T,
+        deadline: Option<Instant>,
+    ) -> Result<(), SendTimeoutError<T>> {
+        // a full queue usually drains again quickly, spin for a bit before parking.
+        let backoff = Backoff::new();
+        let mut val = val;
+        loop {
+            match queue.push(val) {
+                Ok(()) => {
+                    self.inner.record_sent(1, || queue.len());
+                    self.inner.wake_sleepers();
+                    return Ok(());
+                }
+                Err(PushError::Closed(v)) => return Err(SendTimeoutError::Disconnected(v)),
+                Err(PushError::Full(v)) if backoff.is_completed() || timed_out(deadline) => {
+                    val = v;
+                    break;
+                }
+                Err(PushError::Full(v)) => val = v,
+            }
+            backoff.snooze();
+        }
+
+        let mut guard = lock(&self.inner.mu);
+        loop {
+            if timed_out(deadline) {
+                return Err(SendTimeoutError::Timeout(val));
+            }
+
+            self.inner.blocked.fetch_add(1, Ordering::SeqCst);
+            // re-try after announcing ourselves, the reciever might have popped in between
+            // without seeing us.
+            match queue.push(val) {
+                Ok(()) => {
+                    self.inner.record_sent(1, || queue.len());
+                    self.inner.blocked.fetch_sub(1, Ordering::SeqCst);
+                    drop(guard);
+                    self.inner.wake_sleepers();
+                    return Ok(());
+                }
+                Err(PushError::Closed(v)) => {
+                    self.inner.blocked.fetch_sub(1, Ordering::SeqCst);
+                    return Err(SendTimeoutError::Disconnected(v));
+                }
+                Err(PushError::Full(v)) => val = v,
+            }
+            guard = self.inner.park(&self.inner.space, guard, deadline);
+            self.inner.blocked.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+// keeps the head and tail of a queue on separate cache lines.
+#[repr(align(128))]
+struct CachePadded<T>(T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+// exponential backoff for the spin loops of the queues.
+struct Backoff {
+    step: Cell<u32>,
+}
+
+impl Backoff {
+    const SPIN_LIMIT: u32 = 6;
+    const YIELD_LIMIT: u32 = 10;
+
+    fn new() -> Self {
+        Self { step: Cell::new(0) }
+    }
+
+    // lost a race on an atomic, retry shortly.
+    fn spin(&self) {
+        for _ in 0..1 << self.step.get().min(Self::SPIN_LIMIT) {
+            hint::spin_loop();
+        }
+        if self.step.get() <= Self::SPIN_LIMIT {
+            self.step.set(self.step.get() + 1);
+        }
+    }
+
+    // waiting on another thread to finish its part, give it the cpu if it takes a while.
+    fn snooze(&self) {
+        if self.step.get() <= Self::SPIN_LIMIT {
+            for _ in 0..1 << self.step.get() {
+                hint::spin_loop();
+            }
+        } else {
+            // without std there is nobody to yield to, keep spinning.
+            #[cfg(feature = "std")]
+            std::thread::yield_now();
+            #[cfg(not(feature = "std"))]
+            hint::spin_loop();
+        }
+        if self.step.get() <= Self::YIELD_LIMIT {
+            self.step.set(self.step.get() + 1);
+        }
+    }
+
+    // snoozed long enough, time to block instead.
+    fn is_completed(&self) -> bool {
+        self.step.get() > Self::YIELD_LIMIT
+    }
+}
+
+#[test]
+fn unbounded_send_recv() {
+    let (tx, mut rx) = unbounded();
+    for i in 0..100 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        (0..100).collect::<Vec<_>>()
+    );
+    assert_eq!(rx.try_recv(), Err(crate::TryRecvError::Empty));
+    drop(tx);
+    assert_eq!(rx.recv(), Err(crate::RecvError));
+}
+
+#[test]
+fn unbounded_wakes_parked_reciever() {
+    let (tx, mut rx) = unbounded();
+    let handle = thread::spawn(move || rx.iter().sum::<u64>());
+    let senders: Vec<_> = (0..4)
+        .map(|_| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    tx.send(i).unwrap();
+                    if i % 100 == 0 {
+                        // give the reciever a chance to park on an empty queue.
+                        thread::sleep(std::time::Duration::from_millis(1));
+                    }
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+    for s in senders {
+        s.join().unwrap();
+    }
+    assert_eq!(handle.join().unwrap(), 4 * 999 * 1000 / 2);
+}
+
+#[test]
+fn unbounded_send_after_reciever_drop() {
+    let (tx, rx) = unbounded();
+    tx.send(1).unwrap();
+    drop(rx);
+    assert_eq!(tx.send(2), Err(crate::SendErr(2)));
+    assert_eq!(tx.try_send(3), Err(crate::TrySendError::Disconnected(3)));
+}
+
+#[test]
+fn unbounded_poll_recv() {
+    use std::task::{Context, Poll, Waker};
+
+    let (tx, mut rx) = unbounded();
+    let mut cx = Context::from_waker(Waker::noop());
+    assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+    tx.send(1).unwrap();
+    assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Some(1)));
+    // the registration is gone, senders go back to skipping the mutex.
+    assert_eq!(
+        tx.inner.sleeping.load(std::sync::atomic::Ordering::SeqCst),
+        0
+    );
+    drop(tx);
+    assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(None));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn bounded_blocks_when_full() {
+    use std::time::Duration;
+
+    let (tx, mut rx) = bounded(2);
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    assert_eq!(tx.try_send(3), Err(crate::TrySendError::Full(3)));
+    assert_eq!(
+        tx.send_timeout(3, Duration::from_millis(10)),
+        Err(SendTimeoutError::Timeout(3))
+    );
+
+    let handle = thread::spawn(move || {
+        for i in 3..100 {
+            tx.send(i).unwrap();
+        }
+    });
+    for i in 1..100 {
+        assert_eq!(rx.recv(), Ok(i));
+    }
+    handle.join().unwrap();
+    assert_eq!(rx.recv(), Err(crate::RecvError));
+}
+
+#[test]
+fn bounded_reciever_drop_wakes_sender() {
+    let (tx, rx) = bounded(1);
+    tx.send(1).unwrap();
+    let handle = thread::spawn(move || tx.send(2));
+    thread::sleep(std::time::Duration::from_millis(50));
+    drop(rx);
+    assert_eq!(handle.join().unwrap(), Err(crate::SendErr(2)));
+}
+
+#[test]
+fn bounded_send_async_waits_for_room() {
+    use std::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll, Waker},
+    };
+
+    let (tx, mut rx) = bounded(1);
+    let mut cx = Context::from_waker(Waker::noop());
+    tx.send(1).unwrap();
+    let mut fut = tx.send_async(2);
+    assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+    assert_eq!(tx.inner.blocked.load(Ordering::SeqCst), 1);
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Ready(Ok(())));
+    assert_eq!(tx.inner.blocked.load(Ordering::SeqCst), 0);
+    assert_eq!(rx.recv(), Ok(2));
+}
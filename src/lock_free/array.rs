@@ -0,0 +1,313 @@
+// bounded mpmc queue on a ring of slots allocated once up front, Dmitry Vyukov's design in the
+// style of crossbeam's array channel.
+//
+// `head` and `tail` hold an index into the ring plus a lap counter in the bits above
+// `mark_bit`, the `mark_bit` itself is only ever set on `tail` and means the queue got closed.
+// Every slot carries a stamp telling who may touch it next: a sender may write it once the stamp
+// equals `tail`, a reciever may read it once the stamp is `head + 1`.
+
+use alloc::boxed::Box;
+#[cfg(test)]
+use alloc::vec::Vec;
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{self, AtomicUsize, Ordering},
+};
+
+use super::{Backoff, CachePadded, PushError};
+
+struct Slot<T> {
+    stamp: AtomicUsize,
+    msg: UnsafeCell<MaybeUninit<T>>,
+}
+
+pub(crate) struct Array<T> {
+    head: CachePadded<AtomicUsize>,
+    tail: CachePadded<AtomicUsize>,
+    buffer: Box<[Slot<T>]>,
+    cap: usize,
+    // lowest bit above the index, marks a closed queue on `tail`.
+    mark_bit: usize,
+    // what gets added to an index to move it to the same slot one lap later.
+    one_lap: usize,
+}
+
+// SAFETY: messages are only ever moved between threads, each slot is accessed by one sender and
+// one reciever at a time which synchronize through its stamp.
+unsafe impl<T: Send> Send for Array<T> {}
+unsafe impl<T: Send> Sync for Array<T> {}
+
+impl<T> Array<T> {
+    pub(crate) fn new(cap: usize) -> Self {
+        assert!(cap > 0, "capacity must be positive");
+
+        let mark_bit = (cap + 1).next_power_of_two();
+        let buffer = (0..cap)
+            .map(|i| Slot {
+                // ready to be written in the first lap.
+                stamp: AtomicUsize::new(i),
+                msg: UnsafeCell::new(MaybeUninit::uninit()),
+            })
+            .collect();
+        Self {
+            head: CachePadded(AtomicUsize::new(0)),
+            tail: CachePadded(AtomicUsize::new(0)),
+            buffer,
+            cap,
+            mark_bit,
+            one_lap: mark_bit * 2,
+        }
+    }
+
+    // the position following `pos`, wrapping around into the next lap at the end of the ring.
+    fn next(&self, pos: usize) -> usize {
+        let index = pos & (self.mark_bit - 1);
+        let lap = pos & !(self.one_lap - 1);
+        if index + 1 < self.cap {
+            pos + 1
+        } else {
+            lap.wrapping_add(self.one_lap)
+        }
+    }
+
+    pub(crate) fn push(&self, val: T) -> Result<(), PushError<T>> {
+        let backoff = Backoff::new();
+        let mut tail = self.tail.load(Ordering::Relaxed);
+
+        loop {
+            if tail & self.mark_bit != 0 {
+                return Err(PushError::Closed(val));
+            }
+
+            let slot = &self.buffer[tail & (self.mark_bit - 1)];
+            let stamp = slot.stamp.load(Ordering::Acquire);
+            if stamp == tail {
+                // the slot is free in this lap, try to claim it.
+                match self.tail.compare_exchange_weak(
+                    tail,
+                    self.next(tail),
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: claiming the slot gives us exclusive access untill we bump its
+                        // stamp.
+                        unsafe { slot.msg.get().write(MaybeUninit::new(val)) };
+                        slot.stamp.store(tail + 1, Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(t) => {
+                        tail = t;
+                        backoff.spin();
+                    }
+                }
+            } else if stamp.wrapping_add(self.one_lap) == tail + 1 {
+                // the slot still holds a message from the previous lap, the queue might be full.
+                atomic::fence(Ordering::SeqCst);
+                let head = self.head.load(Ordering::Relaxed);
+                if head.wrapping_add(self.one_lap) == tail {
+                    return Err(PushError::Full(val));
+                }
+                // a reciever is just reading it.
+                backoff.spin();
+                tail = self.tail.load(Ordering::Relaxed);
+            } else {
+                // another sender claimed the slot and moved `tail` on, catch up.
+                backoff.snooze();
+                tail = self.tail.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub(crate) fn pop(&self) -> Option<T> {
+        let backoff = Backoff::new();
+        let mut head = self.head.load(Ordering::Relaxed);
+
+        loop {
+            let slot = &self.buffer[head & (self.mark_bit - 1)];
+            let stamp = slot.stamp.load(Ordering::Acquire);
+            if stamp == head + 1 {
+                // the slot holds a message of this lap, try to claim it.
+                match self.head.compare_exchange_weak(
+                    head,
+                    self.next(head),
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: the stamp says the message got written, and claiming the slot
+                        // gives us exclusive access untill we bump its stamp.
+                        let val = unsafe { slot.msg.get().read().assume_init() };
+                        slot.stamp
+                            .store(head.wrapping_add(self.one_lap), Ordering::Release);
+                        return Some(val);
+                    }
+                    Err(h) => {
+                        head = h;
+                        backoff.spin();
+                    }
+                }
+            } else if stamp == head {
+                // nothing written yet, the queue might be empty.
+                atomic::fence(Ordering::SeqCst);
+                let tail = self.tail.load(Ordering::Relaxed);
+                if tail & !self.mark_bit == head {
+                    return None;
+                }
+                // a sender claimed the slot but is still writing to it.
+                backoff.spin();
+                head = self.head.load(Ordering::Relaxed);
+            } else {
+                // another reciever claimed the slot and moved `head` on, catch up.
+                backoff.snooze();
+                head = self.head.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    // makes every later push fail, returns whether this call closed the queue.
+    pub(crate) fn close(&self) -> bool {
+        self.tail.fetch_or(self.mark_bit, Ordering::SeqCst) & self.mark_bit == 0
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        loop {
+            let tail = self.tail.load(Ordering::SeqCst);
+            let head = self.head.load(Ordering::SeqCst);
+            // only trust the pair if `tail` didn't move while we read `head`.
+            if self.tail.load(Ordering::SeqCst) == tail {
+                return self.len_between(head, tail);
+            }
+        }
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        let tail = self.tail.load(Ordering::SeqCst);
+        let head = self.head.load(Ordering::SeqCst);
+        head.wrapping_add(self.one_lap) == tail & !self.mark_bit
+    }
+
+    fn len_between(&self, head: usize, tail: usize) -> usize {
+        let hix = head & (self.mark_bit - 1);
+        let tix = tail & (self.mark_bit - 1);
+        if hix < tix {
+            tix - hix
+        } else if hix > tix {
+            self.cap - hix + tix
+        } else if tail & !self.mark_bit == head {
+            0
+        } else {
+            self.cap
+        }
+    }
+}
+
+impl<T> Drop for Array<T> {
+    fn drop(&mut self) {
+        let head = *self.head.get_mut();
+        let tail = *self.tail.get_mut();
+        let hix = head & (self.mark_bit - 1);
+
+        // drop the messages left between head and tail.
+        for i in 0..self.len_between(head, tail) {
+            let index = (hix + i) % self.cap;
+            // SAFETY: every slot between head and tail holds an initialized message.
+            unsafe { self.buffer[index].msg.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+#[test]
+fn fifo_and_full() {
+    let array = Array::new(3);
+    for round in 0..5 {
+        for i in 0..3 {
+            assert!(array.push(round * 3 + i).is_ok());
+        }
+        assert!(array.is_full());
+        assert!(matches!(array.push(99), Err(PushError::Full(99))));
+        assert_eq!(array.len(), 3);
+        for i in 0..3 {
+            assert_eq!(array.pop(), Some(round * 3 + i));
+        }
+        assert_eq!(array.pop(), None);
+        assert_eq!(array.len(), 0);
+    }
+}
+
+#[test]
+fn push_after_close_fails() {
+    let array = Array::new(2);
+    assert!(array.push(1).is_ok());
+    assert!(array.close());
+    assert!(!array.close());
+    assert!(matches!(array.push(2), Err(PushError::Closed(2))));
+    assert_eq!(array.len(), 1);
+    assert_eq!(array.pop(), Some(1));
+    assert_eq!(array.pop(), None);
+}
+
+#[test]
+fn drops_leftovers() {
+    use std::sync::Arc;
+
+    let val = Arc::new(());
+    let array = Array::new(4);
+    // wrap around the end of the ring once.
+    for _ in 0..3 {
+        assert!(array.push(Arc::clone(&val)).is_ok());
+        array.pop();
+    }
+    for _ in 0..4 {
+        assert!(array.push(Arc::clone(&val)).is_ok());
+    }
+    drop(array);
+    assert_eq!(Arc::strong_count(&val), 1);
+}
+
+#[test]
+fn concurrent_mpmc() {
+    use std::{sync::Arc, thread};
+
+    let array = Arc::new(Array::new(16));
+    let producers: Vec<_> = (0..4)
+        .map(|p| {
+            let array = Arc::clone(&array);
+            thread::spawn(move || {
+                for i in 0..10_000 {
+                    let mut val = p * 10_000 + i;
+                    while let Err(PushError::Full(v)) = array.push(val) {
+                        val = v;
+                        thread::yield_now();
+                    }
+                }
+            })
+        })
+        .collect();
+    let consumers: Vec<_> = (0..4)
+        .map(|_| {
+            let array = Arc::clone(&array);
+            thread::spawn(move || {
+                let mut got = Vec::new();
+                while got.len() < 10_000 {
+                    match array.pop() {
+                        Some(v) => got.push(v),
+                        None => thread::yield_now(),
+                    }
+                }
+                got
+            })
+        })
+        .collect();
+
+    for p in producers {
+        p.join().unwrap();
+    }
+    let mut all: Vec<_> = consumers
+        .into_iter()
+        .flat_map(|c| c.join().unwrap())
+        .collect();
+    all.sort_unstable();
+    assert_eq!(all, (0..40_000).collect::<Vec<_>>());
+}
@@ -0,0 +1,410 @@
+// unbounded mpmc queue made of linked blocks of slots, in the style of crossbeam's list channel.
+//
+// `head` and `tail` are slot indices shifted left by one, leaving the lowest bit as a mark: on
+// `tail` it means the queue got closed, on `head` it caches that the head and tail live in
+// different blocks. Every `LAP` indices the last one is never used, it marks the jump to the next
+// block.
+
+use alloc::boxed::Box;
+#[cfg(test)]
+use alloc::vec::Vec;
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    ptr,
+    sync::atomic::{self, AtomicPtr, AtomicUsize, Ordering},
+};
+
+use super::{Backoff, CachePadded};
+
+// slot states.
+const WRITE: usize = 1;
+const READ: usize = 2;
+const DESTROY: usize = 4;
+
+const LAP: usize = 32;
+const BLOCK_CAP: usize = LAP - 1;
+const SHIFT: usize = 1;
+const MARK_BIT: usize = 1;
+
+struct Slot<T> {
+    msg: UnsafeCell<MaybeUninit<T>>,
+    state: AtomicUsize,
+}
+
+impl<T> Slot<T> {
+    // a sender reserved the slot but might still be writing to it.
+    fn wait_write(&self) {
+        let backoff = Backoff::new();
+        while self.state.load(Ordering::Acquire) & WRITE == 0 {
+            backoff.snooze();
+        }
+    }
+}
+
+struct Block<T> {
+    next: AtomicPtr<Block<T>>,
+    slots: [Slot<T>; BLOCK_CAP],
+}
+
+impl<T> Block<T> {
+    fn new() -> Box<Self> {
+        // SAFETY: all-zero is a valid block, null `next`, empty states and uninit messages.
+        unsafe { Box::new_zeroed().assume_init() }
+    }
+
+    fn wait_next(&self) -> *mut Self {
+        let backoff = Backoff::new();
+        loop {
+            let next = self.next.load(Ordering::Acquire);
+            if !next.is_null() {
+                return next;
+            }
+            backoff.snooze();
+        }
+    }
+
+    // frees the block once every slot from `start` on has been read. If a reader is still busy
+    // with a slot it gets marked with `DESTROY` and that reader carries on with the destruction.
+    unsafe fn destroy(this: *mut Self, start: usize) {
+        // the last slot doesn't need the mark, its reader is the one who started destroying.
+        for i in start..BLOCK_CAP - 1 {
+            let slot = (*this).slots.get_unchecked(i);
+            if slot.state.load(Ordering::Acquire) & READ == 0
+                && slot.state.fetch_or(DESTROY, Ordering::AcqRel) & READ == 0
+            {
+                return;
+            }
+        }
+        drop(Box::from_raw(this));
+    }
+}
+
+struct Position<T> {
+    index: AtomicUsize,
+    block: AtomicPtr<Block<T>>,
+}
+
+pub(crate) struct List<T> {
+    head: CachePadded<Position<T>>,
+    tail: CachePadded<Position<T>>,
+}
+
+// SAFETY: messages are only ever moved between threads, each slot is accessed by one sender and
+// one reciever which synchronize through its state.
+unsafe impl<T: Send> Send for List<T> {}
+unsafe impl<T: Send> Sync for List<T> {}
+
+impl<T> List<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            head: CachePadded(Position {
+                index: AtomicUsize::new(0),
+                block: AtomicPtr::new(ptr::null_mut()),
+            }),
+            tail: CachePadded(Position {
+                index: AtomicUsize::new(0),
+                block: AtomicPtr::new(ptr::null_mut()),
+            }),
+        }
+    }
+
+    // fails only once the queue is closed.
+    pub(crate) fn push(&self, val: T) -> Result<(), T> {
+        let backoff = Backoff::new();
+        let mut tail = self.tail.index.load(Ordering::Acquire);
+        let mut block = self.tail.block.load(Ordering::Acquire);
+        let mut next_block = None;
+
+        loop {
+            if tail & MARK_BIT != 0 {
+                return Err(val);
+            }
+
+            let offset = (tail >> SHIFT) % LAP;
+            // reached the end of the block, wait for the next one to be installed.
+            if offset == BLOCK_CAP {
+                backoff.snooze();
+                tail = self.tail.index.load(Ordering::Acquire);
+                block = self.tail.block.load(Ordering::Acquire);
+                continue;
+            }
+
+            // we'll have to install the next block, allocate it up front so that the others
+            // don't wait on our allocation.
+            if offset + 1 == BLOCK_CAP && next_block.is_none() {
+                next_block = Some(Block::new());
+            }
+
+            // very first message, install the first block.
+            if block.is_null() {
+                let new = Box::into_raw(Block::new());
+                if self
+                    .tail
+                    .block
+                    .compare_exchange(block, new, Ordering::Release, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    self.head.block.store(new, Ordering::Release);
+                    block = new;
+                } else {
+                    // SAFETY: we just leaked it and nobody else saw it.
+                    next_block = Some(unsafe { Box::from_raw(new) });
+                    tail = self.tail.index.load(Ordering::Acquire);
+                    block = self.tail.block.load(Ordering::Acquire);
+                    continue;
+                }
+            }
+
+            let new_tail = tail + (1 << SHIFT);
+            match self.tail.index.compare_exchange_weak(
+                tail,
+                new_tail,
+                Ordering::SeqCst,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => unsafe {
+                    // we took the last slot, install the next block.
+                    if offset + 1 == BLOCK_CAP {
+                        let next_block = Box::into_raw(next_block.unwrap());
+                        self.tail.block.store(next_block, Ordering::Release);
+                        self.tail.index.fetch_add(1 << SHIFT, Ordering::Release);
+                        (*block).next.store(next_block, Ordering::Release);
+                    }
+
+                    let slot = (*block).slots.get_unchecked(offset);
+                    slot.msg.get().write(MaybeUninit::new(val));
+                    slot.state.fetch_or(WRITE, Ordering::Release);
+                    return Ok(());
+                },
+                Err(t) => {
+                    tail = t;
+                    block = self.tail.block.load(Ordering::Acquire);
+                    backoff.spin();
+                }
+            }
+        }
+    }
+
+    pub(crate) fn pop(&self) -> Option<T> {
+        let backoff = Backoff::new();
+        let mut head = self.head.index.load(Ordering::Acquire);
+        let mut block = self.head.block.load(Ordering::Acquire);
+
+        loop {
+            let offset = (head >> SHIFT) % LAP;
+            // reached the end of the block, wait for the next one to be installed.
+            if offset == BLOCK_CAP {
+                backoff.snooze();
+                head = self.head.index.load(Ordering::Acquire);
+                block = self.head.block.load(Ordering::Acquire);
+                continue;
+            }
+
+            let mut new_head = head + (1 << SHIFT);
+            if new_head & MARK_BIT == 0 {
+                atomic::fence(Ordering::SeqCst);
+                let tail = self.tail.index.load(Ordering::Relaxed);
+
+                if head >> SHIFT == tail >> SHIFT {
+                    return None;
+                }
+                // head and tail in different blocks, no need to compare them untill the next one.
+                if (head >> SHIFT) / LAP != (tail >> SHIFT) / LAP {
+                    new_head |= MARK_BIT;
+                }
+            }
+
+            // the first message is still being sent and its block isn't installed yet.
+            if block.is_null() {
+                backoff.snooze();
+                head = self.head.index.load(Ordering::Acquire);
+                block = self.head.block.load(Ordering::Acquire);
+                continue;
+            }
+
+            match self.head.index.compare_exchange_weak(
+                head,
+                new_head,
+                Ordering::SeqCst,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => unsafe {
+                    // we took the last slot, move on to the next block.
+                    if offset + 1 == BLOCK_CAP {
+                        let next = (*block).wait_next();
+                        let mut next_index = (new_head & !MARK_BIT).wrapping_add(1 << SHIFT);
+                        if !(*next).next.load(Ordering::Relaxed).is_null() {
+                            next_index |= MARK_BIT;
+                        }
+
+                        self.head.block.store(next, Ordering::Release);
+                        self.head.index.store(next_index, Ordering::Release);
+                    }
+
+                    let slot = (*block).slots.get_unchecked(offset);
+                    slot.wait_write();
+                    let val = slot.msg.get().read().assume_init();
+
+                    // destroy the block if we read its last slot, or if someone wanted to but had
+                    // to leave it to us since we were still reading.
+                    if offset + 1 == BLOCK_CAP {
+                        Block::destroy(block, 0);
+                    } else if slot.state.fetch_or(READ, Ordering::AcqRel) & DESTROY != 0 {
+                        Block::destroy(block, offset + 1);
+                    }
+                    return Some(val);
+                },
+                Err(h) => {
+                    head = h;
+                    block = self.head.block.load(Ordering::Acquire);
+                    backoff.spin();
+                }
+            }
+        }
+    }
+
+    // makes every later push fail. Returns whether this call closed the queue.
+    pub(crate) fn close(&self) -> bool {
+        self.tail.index.fetch_or(MARK_BIT, Ordering::SeqCst) & MARK_BIT == 0
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        loop {
+            let mut tail = self.tail.index.load(Ordering::SeqCst);
+            let mut head = self.head.index.load(Ordering::SeqCst);
+
+            // only a tail that didn't move in between gives a consistent pair.
+            if self.tail.index.load(Ordering::SeqCst) == tail {
+                tail &= !((1 << SHIFT) - 1);
+                head &= !((1 << SHIFT) - 1);
+
+                // indices on a block end really point at the start of the next block.
+                if (tail >> SHIFT) & (LAP - 1) == LAP - 1 {
+                    tail = tail.wrapping_add(1 << SHIFT);
+                }
+                if (head >> SHIFT) & (LAP - 1) == LAP - 1 {
+                    head = head.wrapping_add(1 << SHIFT);
+                }
+
+                // rotate both so that head falls into the first block.
+                let lap = (head >> SHIFT) / LAP;
+                tail = tail.wrapping_sub((lap * LAP) << SHIFT);
+                head = head.wrapping_sub((lap * LAP) << SHIFT);
+
+                tail >>= SHIFT;
+                head >>= SHIFT;
+
+                // minus the unused index at the end of every block in between.
+                return tail - head - tail / LAP;
+            }
+        }
+    }
+}
+
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        let mut head = *self.head.index.get_mut() & !((1 << SHIFT) - 1);
+        let tail = *self.tail.index.get_mut() & !((1 << SHIFT) - 1);
+        let mut block = *self.head.block.get_mut();
+
+        // drop the messages left between head and tail, freeing the blocks on the way.
+        unsafe {
+            while head != tail {
+                let offset = (head >> SHIFT) % LAP;
+                if offset < BLOCK_CAP {
+                    let slot = (*block).slots.get_unchecked(offset);
+                    (*slot.msg.get()).assume_init_drop();
+                } else {
+                    let next = *(*block).next.get_mut();
+                    drop(Box::from_raw(block));
+                    block = next;
+                }
+                head = head.wrapping_add(1 << SHIFT);
+            }
+
+            if !block.is_null() {
+                drop(Box::from_raw(block));
+            }
+        }
+    }
+}
+
+#[test]
+fn fifo_across_blocks() {
+    let list = List::new();
+    for i in 0..100 {
+        list.push(i).unwrap();
+    }
+    assert_eq!(list.len(), 100);
+    for i in 0..100 {
+        assert_eq!(list.pop(), Some(i));
+    }
+    assert_eq!(list.pop(), None);
+    assert_eq!(list.len(), 0);
+}
+
+#[test]
+fn push_after_close_fails() {
+    let list = List::new();
+    list.push(1).unwrap();
+    assert!(list.close());
+    assert!(!list.close());
+    assert_eq!(list.push(2), Err(2));
+    assert_eq!(list.pop(), Some(1));
+}
+
+#[test]
+fn drops_leftovers() {
+    use std::sync::Arc;
+
+    let val = Arc::new(());
+    let list = List::new();
+    for _ in 0..40 {
+        list.push(Arc::clone(&val)).unwrap();
+    }
+    list.pop();
+    drop(list);
+    assert_eq!(Arc::strong_count(&val), 1);
+}
+
+#[test]
+fn concurrent_mpmc() {
+    use std::{sync::Arc, thread};
+
+    let list = Arc::new(List::new());
+    let producers: Vec<_> = (0..4)
+        .map(|p| {
+            let list = Arc::clone(&list);
+            thread::spawn(move || {
+                for i in 0..10_000 {
+                    list.push(p * 10_000 + i).unwrap();
+                }
+            })
+        })
+        .collect();
+    let consumers: Vec<_> = (0..4)
+        .map(|_| {
+            let list = Arc::clone(&list);
+            thread::spawn(move || {
+                let mut got = Vec::new();
+                while got.len() < 10_000 {
+                    if let Some(v) = list.pop() {
+                        got.push(v);
+                    }
+                }
+                got
+            })
+        })
+        .collect();
+
+    for p in producers {
+        p.join().unwrap();
+    }
+    let mut got: Vec<_> = consumers
+        .into_iter()
+        .flat_map(|c| c.join().unwrap())
+        .collect();
+    got.sort_unstable();
+    assert_eq!(got, (0..40_000).collect::<Vec<_>>());
+}
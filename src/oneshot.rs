@@ -0,0 +1,205 @@
+use alloc::sync::Arc;
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use crate::{
+    lock,
+    sync::{Condvar, Instant, Mutex},
+    waker::Woken,
+    RecvError, RecvTimeoutError, SendErr, TryRecvError,
+};
+
+// a oneshot only ever carries one value, so it skips `Inner` with its queues, counters and
+// waker registries.
+struct Shared<T> {
+    mu: Mutex<State<T>>,
+    cond: Condvar,
+}
+
+struct State<T> {
+    val: Option<T>,
+    // the sender is gone, whether it sent a value or not.
+    sender_gone: bool,
+    reciever_gone: bool,
+    // the task awaiting the reciever.
+    waker: Option<Waker>,
+}
+
+/// The sending half of a [`oneshot`] channel.
+pub struct OneshotSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> OneshotSender<T> {
+    /// Sends the value, consuming the sender. Never blocks.
+    ///
+    /// Fails with [`SendErr`] holding the value if the reciever is gone.
+    pub fn send(self, val: T) -> Result<(), SendErr<T>> {
+        let mut guard = lock(&self.shared.mu);
+        if guard.reciever_gone {
+            return Err(SendErr(val));
+        }
+        guard.val = Some(val);
+        // dropping `self` wakes the reciever.
+        Ok(())
+    }
+
+    /// Whether the reciever is gone, sending would fail.
+    pub fn is_closed(&self) -> bool {
+        lock(&self.shared.mu).reciever_gone
+    }
+}
+
+impl<T> Drop for OneshotSender<T> {
+    fn drop(&mut self) {
+        let mut guard = lock(&self.shared.mu);
+        guard.sender_gone = true;
+        let mut woken = Woken::default();
+        if let Some(waker) = guard.waker.take() {
+            woken.push(waker);
+        }
+        drop(guard);
+        self.shared.cond.notify_one();
+        woken.wake_in_drop();
+    }
+}
+
+/// The recieving half of a [`oneshot`] channel.
+///
+/// Either block on it with [`recv`](OneshotReciever::recv) or `.await` it directly. Both fail
+/// with [`RecvError`] if the sender got dropped without sending.
+pub struct OneshotReciever<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> OneshotReciever<T> {
+    /// Blocks untill the value is sent.
+    pub fn recv(self) -> Result<T, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError)
+    }
+
+    /// Like [`recv`](OneshotReciever::recv) but gives up with [`RecvTimeoutError::Timeout`] once
+    /// `timeout` elapsed. Takes `&mut self` so that it can be retried.
+    #[cfg(feature = "std")]
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_until(Instant::now().checked_add(timeout))
+    }
+
+    fn recv_until(&self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let mut guard = lock(&self.shared.mu);
+        loop {
+            if let Some(v) = guard.val.take() {
+                return Ok(v);
+            }
+            if guard.sender_gone {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            if crate::timed_out(deadline) {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            guard = crate::wait(&self.shared.cond, guard, deadline);
+        }
+    }
+
+    /// Takes the value if it was already sent, without blocking.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut guard = lock(&self.shared.mu);
+        match guard.val.take() {
+            Some(v) => Ok(v),
+            None if guard.sender_gone => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl<T> Future for OneshotReciever<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut guard = lock(&self.shared.mu);
+        if let Some(v) = guard.val.take() {
+            return Poll::Ready(Ok(v));
+        }
+        if guard.sender_gone {
+            return Poll::Ready(Err(RecvError));
+        }
+        match &mut guard.waker {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            waker => *waker = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for OneshotReciever<T> {
+    fn drop(&mut self) {
+        lock(&self.shared.mu).reciever_gone = true;
+    }
+}
+
+/// Creates a channel for sending a single value, e.g. the response to a request.
+///
+/// Much lighter than a [`bounded`](crate::bounded) channel of capacity 1: there is no queue,
+/// only a slot for the one value.
+pub fn oneshot<T>() -> (OneshotSender<T>, OneshotReciever<T>) {
+    let shared = Arc::new(Shared {
+        mu: Mutex::new(State {
+            val: None,
+            sender_gone: false,
+            reciever_gone: false,
+            waker: None,
+        }),
+        cond: Condvar::new(),
+    });
+    (
+        OneshotSender {
+            shared: Arc::clone(&shared),
+        },
+        OneshotReciever { shared },
+    )
+}
+
+#[test]
+fn oneshot_blocking() {
+    let (tx, rx) = oneshot();
+    let handle = std::thread::spawn(move || rx.recv());
+    std::thread::sleep(Duration::from_millis(10));
+    tx.send(5).unwrap();
+    assert_eq!(handle.join().unwrap(), Ok(5));
+}
+
+#[test]
+fn oneshot_async() {
+    let (tx, rx) = oneshot();
+    let handle = std::thread::spawn(move || crate::future::block_on(rx));
+    std::thread::sleep(Duration::from_millis(10));
+    tx.send("pong").unwrap();
+    assert_eq!(handle.join().unwrap(), Ok("pong"));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn oneshot_sender_dropped() {
+    let (tx, mut rx) = oneshot::<i32>();
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    drop(tx);
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(crate::future::block_on(rx), Err(RecvError));
+}
+
+#[test]
+fn oneshot_reciever_dropped() {
+    let (tx, rx) = oneshot();
+    assert!(!tx.is_closed());
+    drop(rx);
+    assert!(tx.is_closed());
+    assert_eq!(tx.send(1), Err(SendErr(1)));
+}
@@ -0,0 +1,260 @@
+//! Priority channels, which hand out the greatest queued message first.
+//!
+//! The messages are kept in a `BinaryHeap` instead of the FIFO `VecDeque` of [`crate::unbounded`].
+//! Messages of equal priority still come out in the order they were sent. To order by a key
+//! instead of the whole message, send [`ByKey`] values.
+//!
+//! Unlike [`crate::Reciever`] there is no local buffer: every `recv` pops the current maximum off
+//! the shared heap, so a message sent later with a higher priority always overtakes the ones
+//! still queued.
+
+#[cfg(test)]
+use alloc::vec::Vec;
+use alloc::{collections::BinaryHeap, sync::Arc};
+use core::{cmp::Ordering, time::Duration};
+
+use crate::{
+    lock,
+    sync::{Condvar, Instant, Mutex},
+    timed_out, wait, RecvError, RecvTimeoutError, SendErr, TryRecvError,
+};
+
+struct Shared<T> {
+    mu: Mutex<Critical<T>>,
+    cond: Condvar,
+}
+
+struct Critical<T> {
+    heap: BinaryHeap<Entry<T>>,
+    // number of messages sent so far, breaks ties between equal priorities.
+    seq: u64,
+    senders: usize,
+    receivers: usize,
+    done: bool,
+}
+
+struct Entry<T> {
+    val: T,
+    seq: u64,
+}
+
+impl<T: Ord> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // the max-heap pops the greatest value, and among equal ones the lowest `seq`.
+        self.val
+            .cmp(&other.val)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl<T: Ord> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord> Eq for Entry<T> {}
+
+/// A message ordered only by its `key`, for sending values which aren't `Ord` themselves (or
+/// should be prioritized by something else).
+#[derive(Debug, Clone, Copy)]
+pub struct ByKey<K, T> {
+    pub key: K,
+    pub val: T,
+}
+
+impl<K: Ord, T> Ord for ByKey<K, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl<K: Ord, T> PartialOrd for ByKey<K, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, T> PartialEq for ByKey<K, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Ord, T> Eq for ByKey<K, T> {}
+
+/// The sending half of a priority channel, can be cloned to send from multiple threads.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Ord> Sender<T> {
+    /// Queues a value, never blocks.
+    ///
+    /// Fails with [`SendErr`] holding the value if all recievers are gone.
+    pub fn send(&self, val: T) -> Result<(), SendErr<T>> {
+        let mut guard = lock(&self.shared.mu);
+        if guard.done {
+            return Err(SendErr(val));
+        }
+
+        let seq = guard.seq;
+        guard.seq += 1;
+        guard.heap.push(Entry { val, seq });
+        drop(guard);
+        self.shared.cond.notify_one();
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        lock(&self.shared.mu).senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut guard = lock(&self.shared.mu);
+        guard.senders -= 1;
+        if guard.senders == 0 {
+            guard.done = true;
+            drop(guard);
+            self.shared.cond.notify_all();
+        }
+    }
+}
+
+/// The recieving half of a priority channel. Can be cloned, each message is recieved exactly
+/// once.
+pub struct Reciever<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Ord> Reciever<T> {
+    /// Blocks untill a message is available and returns the greatest one.
+    ///
+    /// Fails with [`RecvError`] once the channel is empty and all senders are gone.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError)
+    }
+
+    /// Like [`recv`](Reciever::recv) but gives up with [`RecvTimeoutError::Timeout`] once
+    /// `timeout` elapsed.
+    #[cfg(feature = "std")]
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_until(Instant::now().checked_add(timeout))
+    }
+
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let mut guard = lock(&self.shared.mu);
+        loop {
+            if let Some(entry) = guard.heap.pop() {
+                return Ok(entry.val);
+            }
+            if guard.done {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            if timed_out(deadline) {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            guard = wait(&self.shared.cond, guard, deadline);
+        }
+    }
+
+    /// Attempts to recieve the greatest queued message without blocking.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut guard = lock(&self.shared.mu);
+        match guard.heap.pop() {
+            Some(entry) => Ok(entry.val),
+            None if guard.done => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl<T> Clone for Reciever<T> {
+    fn clone(&self) -> Self {
+        lock(&self.shared.mu).receivers += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Reciever<T> {
+    fn drop(&mut self) {
+        let mut guard = lock(&self.shared.mu);
+        guard.receivers -= 1;
+        if guard.receivers == 0 {
+            // set done to true to make senders fail.
+            guard.done = true;
+        }
+    }
+}
+
+/// Creates an unbounded channel which hands out the greatest queued message first.
+pub fn priority<T: Ord>() -> (Sender<T>, Reciever<T>) {
+    let shared = Arc::new(Shared {
+        mu: Mutex::new(Critical {
+            heap: BinaryHeap::new(),
+            seq: 0,
+            senders: 1,
+            receivers: 1,
+            done: false,
+        }),
+        cond: Condvar::new(),
+    });
+    (
+        Sender {
+            shared: Arc::clone(&shared),
+        },
+        Reciever { shared },
+    )
+}
+
+#[test]
+fn greatest_first_fifo_among_equals() {
+    let (tx, mut rx) = priority();
+    for (key, val) in [(1, 'a'), (3, 'b'), (1, 'c'), (3, 'd'), (2, 'e')] {
+        tx.send(ByKey { key, val }).unwrap();
+    }
+    drop(tx);
+    let got: Vec<_> = std::iter::from_fn(|| rx.recv().ok().map(|m| m.val)).collect();
+    assert_eq!(got, ['b', 'd', 'e', 'a', 'c']);
+}
+
+#[test]
+fn later_send_overtakes() {
+    let (tx, mut rx) = priority();
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    assert_eq!(rx.recv(), Ok(2));
+    tx.send(10).unwrap();
+    assert_eq!(rx.recv(), Ok(10));
+    assert_eq!(rx.try_recv(), Ok(1));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn priority_blocking_and_disconnect() {
+    let (tx, mut rx) = priority::<u32>();
+    let handle = std::thread::spawn(move || (rx.recv(), rx.recv()));
+    std::thread::sleep(Duration::from_millis(10));
+    tx.send(7).unwrap();
+    drop(tx);
+    assert_eq!(handle.join().unwrap(), (Ok(7), Err(RecvError)));
+
+    let (tx, rx) = priority();
+    drop(rx);
+    assert_eq!(tx.send(1), Err(SendErr(1)));
+}
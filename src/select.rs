@@ -0,0 +1,706 @@
+use alloc::{sync::Arc, task::Wake, vec, vec::Vec};
+use core::{
+    cell::Cell,
+    sync::atomic::{AtomicBool, Ordering},
+    task::Waker,
+    time::Duration,
+};
+#[cfg(any(feature = "std", test))]
+use std::thread;
+#[cfg(feature = "std")]
+use std::thread::Thread;
+
+use crate::{lock, sync::Instant, timed_out, ReadyTimeoutError, Reciever, Sender, TryReadyError};
+
+// an operation `Select` can wait on.
+pub(crate) trait Handle {
+    // whether the operation would complete, or fail with a disconnect, without blocking.
+    fn is_ready(&self) -> bool;
+    // asks the channel to wake `waker` whenever the operation might have become ready.
+    fn register(&self, id: &mut Option<usize>, waker: &Waker);
+    fn unregister(&self, id: &mut Option<usize>);
+}
+
+impl<T> Handle for Reciever<T> {
+    fn is_ready(&self) -> bool {
+        if !self.local_buf.is_empty() {
+            return true;
+        }
+        if self.inner.queue.as_ref().is_some_and(|q| q.len() > 0) {
+            return true;
+        }
+
+        let guard = lock(&self.inner.mu);
+        guard.slot.is_some() || !guard.buf.is_empty() || guard.done
+    }
+
+    fn register(&self, id: &mut Option<usize>, waker: &Waker) {
+        let mut guard = lock(&self.inner.mu);
+        self.inner.watch_recv(&mut guard, id, waker);
+    }
+
+    fn unregister(&self, id: &mut Option<usize>) {
+        let mut guard = lock(&self.inner.mu);
+        self.inner.unwatch_recv(&mut guard, id);
+    }
+}
+
+impl<T> Handle for Sender<T> {
+    fn is_ready(&self) -> bool {
+        let guard = lock(&self.inner.mu);
+        if guard.done {
+            return true;
+        }
+
+        match self.inner.cap {
+            // a rendezvous send can only go through if a reciever is waiting for it.
+            Some(0) => guard.slot.is_none() && guard.has_takers(),
+            _ => !self.inner.is_full(&guard),
+        }
+    }
+
+    fn register(&self, id: &mut Option<usize>, waker: &Waker) {
+        let mut guard = lock(&self.inner.mu);
+        self.inner.watch_send(&mut guard, id, waker);
+    }
+
+    fn unregister(&self, id: &mut Option<usize>) {
+        let mut guard = lock(&self.inner.mu);
+        self.inner.unwatch_send(&mut guard, id);
+    }
+}
+
+/// Waits on several channel operations at once.
+///
+/// Operations are added with [`recv`](Select::recv) and [`send`](Select::send), which return
+/// the index [`ready`](Select::ready) reports back once that operation can go through without
+/// blocking (a disconnected channel counts as ready). Readiness is only a hint: another
+/// reciever might take the message first, so the operation should be attempted with
+/// `try_recv` / `try_send` and the selection retried if it fails. The [`select!`](crate::select)
+/// macro does exactly that.
+///
+/// A rendezvous send is only ready while a reciever is blocked in `recv`, so selecting on both
+/// ends of the same rendezvous channel never completes.
+pub struct Select<'a> {
+    handles: Vec<&'a dyn Handle>,
+}
+
+impl<'a> Select<'a> {
+    /// An empty selection, add operations with [`recv`](Select::recv) and
+    /// [`send`](Select::send).
+    pub fn new() -> Self {
+        Self {
+            handles: Vec::new(),
+        }
+    }
+
+    /// Adds a recieve operation, returning its index.
+    pub fn recv<T>(&mut self, rx: &'a Reciever<T>) -> usize {
+        self.handles.push(rx);
+        self.handles.len() - 1
+    }
+
+    /// Adds a send operation, returning its index.
+    pub fn send<T>(&mut self, tx: &'a Sender<T>) -> usize {
+        self.handles.push(tx);
+        self.handles.len() - 1
+    }
+
+    /// Returns the index of an operation which is ready right now, if any.
+    pub fn try_ready(&mut self) -> Result<usize, TryReadyError> {
+        self.find_ready().ok_or(TryReadyError)
+    }
+
+    /// Blocks untill one of the operations is ready and returns its index.
+    ///
+    /// # Panics
+    ///
+    /// If no operations were added, nothing could ever wake it up.
+    pub fn ready(&mut self) -> usize {
+        assert!(
+            !self.handles.is_empty(),
+            "select without any operations would block forever"
+        );
+        self.ready_until(None)
+            .expect("select without a deadline timed out")
+    }
+
+    /// Like [`ready`](Select::ready) but gives up once `timeout` elapsed.
+    #[cfg(feature = "std")]
+    pub fn ready_timeout(&mut self, timeout: Duration) -> Result<usize, ReadyTimeoutError> {
+        self.ready_until(Instant::now().checked_add(timeout))
+    }
+
+    /// Like [`ready_timeout`](Select::ready_timeout) but waits untill an absolute `deadline`.
+    #[cfg(feature = "std")]
+    pub fn ready_deadline(&mut self, deadline: Instant) -> Result<usize, ReadyTimeoutError> {
+        self.ready_until(Some(deadline))
+    }
+
+    fn ready_until(&mut self, deadline: Option<Instant>) -> Result<usize, ReadyTimeoutError> {
+        loop {
+            if let Some(i) = self.find_ready() {
+                return Ok(i);
+            }
+            if timed_out(deadline) {
+                return Err(ReadyTimeoutError);
+            }
+
+            // register on every channel and park untill one of them signals a change.
+            let signal = Arc::new(Signal {
+                #[cfg(feature = "std")]
+                thread: thread::current(),
+                woken: AtomicBool::new(false),
+            });
+            let waker = Waker::from(Arc::clone(&signal));
+            let mut ids = vec![None; self.handles.len()];
+            for (h, id) in self.handles.iter().zip(&mut ids) {
+                h.register(id, &waker);
+            }
+            // something might have become ready before we got registered.
+            if self.find_ready().is_none() {
+                signal.wait(deadline);
+            }
+            for (h, id) in self.handles.iter().zip(&mut ids) {
+                h.unregister(id);
+            }
+        }
+    }
+
+    fn find_ready(&self) -> Option<usize> {
+        let n = self.handles.len();
+        if n == 0 {
+            return None;
+        }
+
+        // start at a random operation so that a constantly ready one doesn't starve the rest.
+        let start = random() % n;
+        (0..n)
+            .map(|i| (start + i) % n)
+            .find(|&i| self.handles[i].is_ready())
+    }
+}
+
+impl Default for Select<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// wakes up the thread blocked in `Select`. Without std there is no thread to park, the flag is
+// spun on instead.
+struct Signal {
+    #[cfg(feature = "std")]
+    thread: Thread,
+    woken: AtomicBool,
+}
+
+impl Signal {
+    #[cfg(not(feature = "std"))]
+    fn wait(&self, deadline: Option<Instant>) {
+        if let Some(deadline) = deadline {
+            match deadline {}
+        }
+        while !self.woken.load(Ordering::Acquire) {
+            core::hint::spin_loop();
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn wait(&self, deadline: Option<Instant>) {
+        // park can return spureously, the flag tells a real wake up apart.
+        while !self.woken.load(Ordering::Acquire) {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return;
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+                None => thread::park(),
+            }
+        }
+    }
+}
+
+impl Wake for Signal {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        #[cfg(feature = "std")]
+        self.thread.unpark();
+    }
+}
+
+// cheap per-thread xorshift, good enough to spread the starting point of a selection.
+#[cfg(feature = "std")]
+fn random() -> usize {
+    thread_local! {
+        static STATE: Cell<u32> = const { Cell::new(0x9e37_79b9) };
+    }
+
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        state.set(x);
+        x as usize
+    })
+}
+
+// without thread locals just take turns, which spreads the starting point just as well.
+#[cfg(not(feature = "std"))]
+fn random() -> usize {
+    use core::sync::atomic::AtomicUsize;
+
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Blocks on several channel operations and runs the arm of the first one which completes.
+///
+/// Supported arms:
+/// - `recv(rx) -> res => body`: `res` is a `Result<T, RecvError>`.
+/// - `send(tx, val) -> res => body`: `res` is a `Result<(), SendErr<T>>`. `val` is only
+///   evaluated once this arm gets picked, but variables it moves out of are moved into the
+///   `select!` right away (and dropped if another arm runs).
+/// - `default => body`: runs if no operation is ready right away.
+/// - `default(timeout) => body`: runs if no operation became ready within `timeout`, needs the
+///   `std` feature.
+///
+/// Each channel expression is evaluated once, `rx` has to be a place which can be borrowed
+/// mutably.
+///
+/// Panics if there are neither operations nor a `default` arm, like [`Select::ready`].
+///
+/// ```
+/// use chanus::{select, unbounded};
+///
+/// let (_work_tx, mut work) = unbounded::<u32>();
+/// let (stop_tx, mut stop) = unbounded::<()>();
+/// stop_tx.send(()).unwrap();
+///
+/// let stopped = select! {
+///     recv(work) -> job => false,
+///     recv(stop) -> _ => true,
+///     default => false,
+/// };
+/// assert!(stopped);
+/// ```
+#[macro_export]
+macro_rules! select {
+    ($($tokens:tt)*) => {
+        $crate::__select!(
+            @parse [] []
+            (
+                __arm0 __arm1 __arm2 __arm3 __arm4 __arm5 __arm6 __arm7 __arm8 __arm9 __arm10
+                __arm11 __arm12 __arm13 __arm14 __arm15 __arm16 __arm17 __arm18 __arm19 __arm20
+                __arm21 __arm22 __arm23 __arm24 __arm25 __arm26 __arm27 __arm28 __arm29 __arm30
+                __arm31
+            )
+            $($tokens)*
+        )
+    };
+}
+
+// the parsing rules normalize every arm into `(kind ident (args) (res) (body))`, picking a fresh
+// identifier for the arm's state out of the list passed in by `select!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __select {
+    (@parse [$($arms:tt)*] [$($default:tt)*] ($id:ident $($ids:ident)*)
+        recv($rx:expr) -> $res:pat => $body:expr, $($rest:tt)*) => {
+        $crate::__select!(
+            @parse [$($arms)* (recv $id ($rx) ($res) ($body))] [$($default)*] ($($ids)*)
+            $($rest)*
+        )
+    };
+    (@parse [$($arms:tt)*] [$($default:tt)*] ($id:ident $($ids:ident)*)
+        recv($rx:expr) -> $res:pat => $body:block $($rest:tt)*) => {
+        $crate::__select!(
+            @parse [$($arms)* (recv $id ($rx) ($res) ($body))] [$($default)*] ($($ids)*)
+            $($rest)*
+        )
+    };
+    (@parse [$($arms:tt)*] [$($default:tt)*] ($id:ident $($ids:ident)*)
+        recv($rx:expr) -> $res:pat => $body:expr) => {
+        $crate::__select!(
+            @parse [$($arms)* (recv $id ($rx) ($res) ($body))] [$($default)*] ($($ids)*)
+        )
+    };
+    (@parse [$($arms:tt)*] [$($default:tt)*] ($id:ident $($ids:ident)*)
+        send($tx:expr, $val:expr) -> $res:pat => $body:expr, $($rest:tt)*) => {
+        $crate::__select!(
+            @parse [$($arms)* (send $id ($tx, $val) ($res) ($body))] [$($default)*] ($($ids)*)
+            $($rest)*
+        )
+    };
+    (@parse [$($arms:tt)*] [$($default:tt)*] ($id:ident $($ids:ident)*)
+        send($tx:expr, $val:expr) -> $res:pat => $body:block $($rest:tt)*) => {
+        $crate::__select!(
+            @parse [$($arms)* (send $id ($tx, $val) ($res) ($body))] [$($default)*] ($($ids)*)
+            $($rest)*
+        )
+    };
+    (@parse [$($arms:tt)*] [$($default:tt)*] ($id:ident $($ids:ident)*)
+        send($tx:expr, $val:expr) -> $res:pat => $body:expr) => {
+        $crate::__select!(
+            @parse [$($arms)* (send $id ($tx, $val) ($res) ($body))] [$($default)*] ($($ids)*)
+        )
+    };
+    (@parse [$($arms:tt)*] [] $ids:tt default => $body:expr, $($rest:tt)*) => {
+        $crate::__select!(@parse [$($arms)*] [now ($body)] $ids $($rest)*)
+    };
+    (@parse [$($arms:tt)*] [] $ids:tt default => $body:block $($rest:tt)*) => {
+        $crate::__select!(@parse [$($arms)*] [now ($body)] $ids $($rest)*)
+    };
+    (@parse [$($arms:tt)*] [] $ids:tt default => $body:expr) => {
+        $crate::__select!(@parse [$($arms)*] [now ($body)] $ids)
+    };
+    (@parse [$($arms:tt)*] [] $ids:tt
+        default($timeout:expr) => $body:expr, $($rest:tt)*) => {
+        $crate::__select!(@parse [$($arms)*] [(timeout ($timeout)) ($body)] $ids $($rest)*)
+    };
+    (@parse [$($arms:tt)*] [] $ids:tt default($timeout:expr) => $body:block $($rest:tt)*) => {
+        $crate::__select!(@parse [$($arms)*] [(timeout ($timeout)) ($body)] $ids $($rest)*)
+    };
+    (@parse [$($arms:tt)*] [] $ids:tt default($timeout:expr) => $body:expr) => {
+        $crate::__select!(@parse [$($arms)*] [(timeout ($timeout)) ($body)] $ids)
+    };
+    (@parse [$($arms:tt)*] [$($default:tt)*] $ids:tt) => {
+        $crate::__select!(@gen [$($arms)*] [$($default)*])
+    };
+
+    (@gen [$(($kind:ident $id:ident $args:tt ($res:pat) $body:tt))*] [$($default:tt)*]) => {{
+        $( $crate::__select!(@bind $kind $id $args); )*
+        let __deadline = $crate::__select!(@deadline $($default)*);
+        let __chosen: ::core::option::Option<usize> = loop {
+            let __ready = {
+                let mut __sel = $crate::Select::new();
+                $( $crate::__select!(@register $kind __sel $id); )*
+                $crate::__select!(@wait __sel __deadline $($default)*)
+            };
+            let __ready = match __ready {
+                ::core::option::Option::Some(i) => i,
+                ::core::option::Option::None => break ::core::option::Option::None,
+            };
+
+            // operations are registered in arm order, so the index tells the arm apart.
+            let mut __n = 0usize;
+            $(
+                if __n == __ready && $crate::__select!(@attempt $kind $id) {
+                    break ::core::option::Option::Some(__ready);
+                }
+                __n += 1;
+            )*
+        };
+        match __chosen {
+            ::core::option::Option::None => $crate::__select!(@default $($default)*),
+            ::core::option::Option::Some(_) => {
+                $(
+                    if let ::core::option::Option::Some(__res) = $id.0.take() {
+                        let $res = __res;
+                        $body
+                    } else
+                )*
+                { ::core::unreachable!() }
+            }
+        }
+    }};
+
+    (@bind recv $id:ident ($rx:expr)) => {
+        let mut $id = (::core::option::Option::None, &mut $rx);
+    };
+    // the value is only produced once the arm gets picked, then kept around in case another
+    // sender wins the race for the room.
+    (@bind send $id:ident ($tx:expr, $val:expr)) => {
+        let mut $id = (
+            ::core::option::Option::None,
+            &$tx,
+            ::core::option::Option::None,
+            ::core::option::Option::Some(|| $val),
+        );
+    };
+
+    (@register recv $sel:ident $id:ident) => {
+        $sel.recv(&*$id.1)
+    };
+    (@register send $sel:ident $id:ident) => {
+        $sel.send(&*$id.1)
+    };
+
+    (@attempt recv $id:ident) => {
+        match $id.1.try_recv() {
+            ::core::result::Result::Ok(v) => {
+                $id.0 = ::core::option::Option::Some(::core::result::Result::Ok(v));
+                true
+            }
+            ::core::result::Result::Err($crate::TryRecvError::Disconnected) => {
+                $id.0 = ::core::option::Option::Some(
+                    ::core::result::Result::Err($crate::RecvError),
+                );
+                true
+            }
+            ::core::result::Result::Err($crate::TryRecvError::Empty) => false,
+        }
+    };
+    (@attempt send $id:ident) => {
+        match $id.1.try_send(match $id.2.take() {
+            ::core::option::Option::Some(v) => v,
+            ::core::option::Option::None => ($id.3.take().unwrap())(),
+        }) {
+            ::core::result::Result::Ok(()) => {
+                $id.0 = ::core::option::Option::Some(::core::result::Result::Ok(()));
+                true
+            }
+            ::core::result::Result::Err($crate::TrySendError::Disconnected(v)) => {
+                $id.0 = ::core::option::Option::Some(
+                    ::core::result::Result::Err($crate::SendErr(v)),
+                );
+                true
+            }
+            ::core::result::Result::Err($crate::TrySendError::Full(v)) => {
+                // lost the race, keep the value for the next round.
+                $id.2 = ::core::option::Option::Some(v);
+                false
+            }
+        }
+    };
+
+    (@deadline) => {
+        ()
+    };
+    (@deadline now $body:tt) => {
+        ()
+    };
+    (@deadline (timeout $timeout:tt) $body:tt) => {
+        ::std::time::Instant::now() + $timeout
+    };
+
+    (@wait $sel:ident $deadline:ident) => {
+        ::core::option::Option::Some($sel.ready())
+    };
+    (@wait $sel:ident $deadline:ident now $body:tt) => {
+        $sel.try_ready().ok()
+    };
+    (@wait $sel:ident $deadline:ident (timeout $timeout:tt) $body:tt) => {
+        $sel.ready_deadline($deadline).ok()
+    };
+
+    (@default) => {
+        ::core::unreachable!()
+    };
+    (@default $mode:tt $body:tt) => {
+        $body
+    };
+}
+
+#[test]
+fn ready_picks_ready_reciever() {
+    let (tx1, rx1) = crate::unbounded::<i32>();
+    let (tx2, rx2) = crate::unbounded::<i32>();
+    tx2.send(1).unwrap();
+
+    let mut sel = Select::new();
+    let i1 = sel.recv(&rx1);
+    let i2 = sel.recv(&rx2);
+    assert_eq!(sel.try_ready(), Ok(i2));
+    assert_eq!(sel.ready(), i2);
+    drop(tx1);
+    // a disconnected channel is ready as well.
+    assert!(sel.try_ready().is_ok());
+}
+
+#[test]
+fn ready_blocks_untill_send() {
+    let (tx, rx1) = crate::unbounded::<i32>();
+    let (_tx2, rx2) = crate::unbounded::<i32>();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        tx.send(1).unwrap();
+    });
+
+    let mut sel = Select::new();
+    let i1 = sel.recv(&rx1);
+    sel.recv(&rx2);
+    assert_eq!(sel.ready(), i1);
+    handle.join().unwrap();
+}
+
+#[test]
+#[should_panic(expected = "select without any operations")]
+fn ready_without_operations_panics() {
+    Select::new().ready();
+}
+
+#[test]
+#[should_panic(expected = "select without any operations")]
+fn select_macro_without_arms_panics() {
+    crate::select! {}
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn ready_timeout_expires() {
+    let (_tx, rx) = crate::unbounded::<i32>();
+    let mut sel = Select::new();
+    sel.recv(&rx);
+    assert_eq!(sel.try_ready(), Err(TryReadyError));
+    assert_eq!(
+        sel.ready_timeout(Duration::from_millis(20)),
+        Err(ReadyTimeoutError)
+    );
+}
+
+#[test]
+fn send_ready_after_local_buf_consumed() {
+    let (tx, mut rx) = crate::bounded(1);
+    tx.send(1).unwrap();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        rx.recv().unwrap();
+        rx
+    });
+
+    let mut sel = Select::new();
+    let i = sel.send(&tx);
+    assert_eq!(sel.ready(), i);
+    assert_eq!(tx.try_send(2), Ok(()));
+    handle.join().unwrap();
+}
+
+#[test]
+fn select_macro_recv() {
+    let (tx1, mut rx1) = crate::unbounded::<i32>();
+    let (tx2, mut rx2) = crate::unbounded::<&str>();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        tx2.send("hi").unwrap();
+    });
+
+    let got = select! {
+        recv(rx1) -> msg => panic!("unexpected {:?}", msg),
+        recv(rx2) -> msg => msg,
+    };
+    assert_eq!(got, Ok("hi"));
+    handle.join().unwrap();
+
+    // the sender of rx2 is gone now.
+    let got = select! {
+        recv(rx2) -> msg => { msg }
+    };
+    assert_eq!(got, Err(crate::RecvError));
+}
+
+#[test]
+fn select_macro_send_and_default() {
+    let (tx, mut rx) = crate::bounded(1);
+    let sent = select! {
+        send(tx, 1) -> res => res.is_ok(),
+        default => false,
+    };
+    assert!(sent);
+
+    // full now, so the default arm runs.
+    let sent = select! {
+        send(tx, 2) -> res => { res.is_ok() }
+        default => false
+    };
+    assert!(!sent);
+    assert_eq!(rx.recv(), Ok(1));
+
+    drop(rx);
+    let res = select! {
+        send(tx, 3) -> res => res,
+    };
+    assert_eq!(res, Err(crate::SendErr(3)));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn select_macro_timeout() {
+    let (_tx, mut rx) = crate::unbounded::<i32>();
+    let start = Instant::now();
+    let timed_out = select! {
+        recv(rx) -> _ => false,
+        default(Duration::from_millis(30)) => true,
+    };
+    assert!(timed_out);
+    assert!(start.elapsed() >= Duration::from_millis(30));
+}
+
+#[test]
+fn select_macro_rendezvous() {
+    let (tx, mut rx) = crate::rendezvous::<i32>();
+    let handle = thread::spawn(move || rx.recv());
+    let res = select! {
+        send(tx, 5) -> res => res,
+    };
+    assert_eq!(res, Ok(()));
+    assert_eq!(handle.join().unwrap(), Ok(5));
+}
+
+#[test]
+fn selecting_reciever_is_no_taker() {
+    let (tx, rx) = crate::rendezvous::<i32>();
+    let (other_tx, other_rx) = crate::unbounded::<i32>();
+    let inner = Arc::clone(&tx.inner);
+    let handle = thread::spawn(move || {
+        let mut sel = Select::new();
+        sel.recv(&rx);
+        let other = sel.recv(&other_rx);
+        sel.ready() == other
+    });
+    while lock(&inner.mu).recv_wakers.is_empty() {
+        thread::yield_now();
+    }
+
+    // the selection might well pick another operation, so it can't take the value.
+    assert_eq!(tx.try_send(1), Err(crate::TrySendError::Full(1)));
+    other_tx.send(2).unwrap();
+    assert!(handle.join().unwrap());
+    assert_eq!(tx.len(), 0);
+}
+
+#[test]
+fn select_macro_evaluates_send_value_lazily() {
+    use core::sync::atomic::AtomicUsize;
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+    struct Loud(i32);
+    impl Drop for Loud {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let (tx, _rx) = crate::bounded::<Loud>(0);
+    let (stop_tx, mut stop) = crate::unbounded::<()>();
+    stop_tx.send(()).unwrap();
+    let mut made = 0;
+    let stopped = select! {
+        send(tx, { made += 1; Loud(42) }) -> _ => false,
+        recv(stop) -> _ => true,
+    };
+    assert!(stopped);
+    // the send arm never got picked, so no value was made (and dropped).
+    assert_eq!(made, 0);
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 0);
+
+    // once picked it is made only once, even if the selection goes round again after losing a
+    // race. Moving a variable in works just as well.
+    let (tx, mut rx) = crate::rendezvous::<Loud>();
+    let handle = thread::spawn(move || rx.recv().map(|v| v.0));
+    let val = Loud(7);
+    let sent = select! {
+        send(tx, { made += 1; val }) -> res => res.is_ok(),
+    };
+    assert!(sent);
+    assert_eq!(made, 1);
+    assert_eq!(handle.join().unwrap(), Ok(7));
+}
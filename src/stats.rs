@@ -0,0 +1,347 @@
+use alloc::boxed::Box;
+#[cfg(test)]
+use alloc::vec::Vec;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64;
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+#[cfg(not(target_has_atomic = "64"))]
+use crate::sync::{lock, Mutex};
+
+/// Snapshot of the counters of a channel built with [`Builder::stats`](crate::Builder::stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    /// Messages sent so far.
+    pub sent: u64,
+    /// Messages recieved so far.
+    pub received: u64,
+    /// Highest number of messages in flight at once, see [`Sender::len`](crate::Sender::len).
+    pub peak_depth: usize,
+    /// Number of times a reciever parked waiting for a message.
+    pub parks: u64,
+    /// Number of times a parked reciever woke up without anything to do.
+    pub spurious_wakeups: u64,
+    /// Total time recievers and senders spent parked.
+    pub blocked_time: Duration,
+}
+
+// a `u64` counter, updated with relaxed atomics since the counters are only ever read as a
+// whole for monitoring. Some 32 bit targets (like powerpc, mips or armv5te linux) have no 64 bit
+// atomics, there it falls back to a lock.
+#[cfg(target_has_atomic = "64")]
+#[derive(Default)]
+struct Counter(AtomicU64);
+
+#[cfg(target_has_atomic = "64")]
+impl Counter {
+    fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    fn max(&self, n: u64) {
+        self.0.fetch_max(n, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(not(target_has_atomic = "64"))]
+struct Counter(Mutex<u64>);
+
+#[cfg(not(target_has_atomic = "64"))]
+impl Default for Counter {
+    fn default() -> Self {
+        Self(Mutex::new(0))
+    }
+}
+
+#[cfg(not(target_has_atomic = "64"))]
+impl Counter {
+    // wraps around like `fetch_add` does.
+    fn add(&self, n: u64) {
+        let mut c = lock(&self.0);
+        *c = c.wrapping_add(n);
+    }
+
+    fn max(&self, n: u64) {
+        let mut c = lock(&self.0);
+        *c = (*c).max(n);
+    }
+
+    fn get(&self) -> u64 {
+        *lock(&self.0)
+    }
+}
+
+// the live counters behind `ChannelStats`.
+#[derive(Default)]
+pub(crate) struct Stats {
+    sent: Counter,
+    received: Counter,
+    peak_depth: AtomicUsize,
+    parks: Counter,
+    spurious_wakeups: Counter,
+    blocked_nanos: Counter,
+}
+
+impl Stats {
+    // `depth` is the number of messages in flight right after sending.
+    pub(crate) fn sent(&self, n: usize, depth: usize) {
+        self.sent.add(n as u64);
+        self.peak_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    pub(crate) fn received(&self, n: usize) {
+        self.received.add(n as u64);
+    }
+
+    pub(crate) fn parked(&self) {
+        self.parks.add(1);
+    }
+
+    pub(crate) fn spurious_wakeup(&self) {
+        self.spurious_wakeups.add(1);
+    }
+
+    pub(crate) fn blocked(&self, time: Duration) {
+        let nanos = u64::try_from(time.as_nanos()).unwrap_or(u64::MAX);
+        self.blocked_nanos.add(nanos);
+    }
+
+    pub(crate) fn snapshot(&self) -> ChannelStats {
+        ChannelStats {
+            sent: self.sent.get(),
+            received: self.received.get(),
+            peak_depth: self.peak_depth.load(Ordering::Relaxed),
+            parks: self.parks.get(),
+            spurious_wakeups: self.spurious_wakeups.get(),
+            blocked_time: Duration::from_nanos(self.blocked_nanos.get()),
+        }
+    }
+}
+
+// every power of two of nanoseconds is split into this many linear buckets, keeping the
+// percentiles within 1/8 of the actual value.
+const SUB_BUCKETS: u64 = 8;
+const SUB_BITS: u32 = SUB_BUCKETS.trailing_zeros();
+const BUCKETS: usize = ((64 - SUB_BITS + 1) as u64 * SUB_BUCKETS) as usize;
+
+fn bucket(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS {
+        return nanos as usize;
+    }
+    let exp = 63 - nanos.leading_zeros();
+    let sub = (nanos >> (exp - SUB_BITS)) & (SUB_BUCKETS - 1);
+    ((exp - SUB_BITS + 1) as u64 * SUB_BUCKETS + sub) as usize
+}
+
+// the highest value falling into `bucket`.
+fn bucket_max(bucket: usize) -> u64 {
+    let bucket = bucket as u64;
+    if bucket < SUB_BUCKETS {
+        return bucket;
+    }
+    let shift = bucket / SUB_BUCKETS - 1;
+    let low = (SUB_BUCKETS + bucket % SUB_BUCKETS) << shift;
+    low + ((1 << shift) - 1)
+}
+
+/// Snapshot of the enqueue-to-dequeue latencies of a channel built with
+/// [`Builder::latency`](crate::Builder::latency).
+///
+/// The latencies are kept in fixed buckets, percentiles are accurate to about 12%.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: Box<[u64]>,
+    count: u64,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    /// Number of recorded messages.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The latency `q` (between 0 and 1) of the messages stayed below, zero if nothing was
+    /// recorded yet.
+    pub fn percentile(&self, q: f64) -> Duration {
+        // rounded up by hand, `f64::ceil` needs std.
+        let exact = self.count as f64 * q.clamp(0.0, 1.0);
+        let mut rank = exact as u64;
+        if (rank as f64) < exact {
+            rank += 1;
+        }
+        let rank = rank.max(1);
+        let mut seen = 0;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Duration::from_nanos(bucket_max(i)).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// The median latency, see [`percentile`](Self::percentile).
+    pub fn p50(&self) -> Duration {
+        self.percentile(0.5)
+    }
+
+    /// The latency 99% of the messages stayed below, see [`percentile`](Self::percentile).
+    pub fn p99(&self) -> Duration {
+        self.percentile(0.99)
+    }
+
+    /// The exact highest latency recorded.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+}
+
+// the live buckets behind `LatencyHistogram`.
+pub(crate) struct Latency {
+    buckets: Box<[Counter]>,
+    max_nanos: Counter,
+}
+
+impl Latency {
+    pub(crate) fn new() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| Counter::default()).collect(),
+            max_nanos: Counter::default(),
+        }
+    }
+
+    pub(crate) fn record(&self, latency: Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.buckets[bucket(nanos)].add(1);
+        self.max_nanos.max(nanos);
+    }
+
+    pub(crate) fn snapshot(&self) -> LatencyHistogram {
+        let buckets: Box<[u64]> = self.buckets.iter().map(Counter::get).collect();
+        LatencyHistogram {
+            count: buckets.iter().sum(),
+            buckets,
+            max: Duration::from_nanos(self.max_nanos.get()),
+        }
+    }
+}
+
+#[test]
+fn buckets_are_contiguous() {
+    for nanos in (0..10_000).chain([u64::MAX / 3, u64::MAX]) {
+        let b = bucket(nanos);
+        assert!(nanos <= bucket_max(b));
+        assert!(b == 0 || nanos > bucket_max(b - 1));
+    }
+    assert_eq!(bucket(u64::MAX), BUCKETS - 1);
+}
+
+#[test]
+fn histogram_percentiles() {
+    let latency = Latency::new();
+    for ms in 1..=100 {
+        latency.record(Duration::from_millis(ms));
+    }
+    let hist = latency.snapshot();
+    assert_eq!(hist.count(), 100);
+    assert_eq!(hist.max(), Duration::from_millis(100));
+    let p50 = hist.p50().as_secs_f64() * 1e3;
+    assert!((50.0..=50.0 * 1.125).contains(&p50), "{p50}");
+    let p99 = hist.p99().as_secs_f64() * 1e3;
+    assert!((99.0..=100.0).contains(&p99), "{p99}");
+}
+
+#[test]
+fn counts_traffic() {
+    let (tx, mut rx) = crate::Builder::new().bounded(8).stats().build();
+    for i in 0..3 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(rx.recv(), Ok(0));
+    tx.send(3).unwrap();
+    let mut buf = Vec::new();
+    // the local buffer first, then the shared one.
+    rx.recv_many(&mut buf, 10).unwrap();
+    rx.recv_many(&mut buf, 10).unwrap();
+    assert_eq!(buf, [1, 2, 3]);
+    let stats = rx.stats().unwrap();
+    assert_eq!((stats.sent, stats.received, stats.peak_depth), (4, 4, 3));
+    assert_eq!(stats.parks, 0);
+
+    assert_eq!(crate::unbounded::<()>().0.stats(), None);
+}
+
+#[test]
+fn counts_parks_and_blocked_time() {
+    for builder in [crate::Builder::new(), crate::Builder::new().lock_free()] {
+        let (tx, mut rx) = builder.stats().build();
+        let handle = std::thread::spawn(move || rx.recv());
+        std::thread::sleep(Duration::from_millis(50));
+        tx.send(1).unwrap();
+        assert_eq!(handle.join().unwrap(), Ok(1));
+
+        let stats = tx.stats().unwrap();
+        assert_eq!((stats.sent, stats.received), (1, 1));
+        assert!(stats.parks >= 1);
+        // without std there is no clock to measure it with.
+        #[cfg(feature = "std")]
+        assert!(stats.blocked_time >= Duration::from_millis(10));
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn latency_follows_local_buf() {
+    let (tx, mut rx) = crate::Builder::new().latency().build();
+    for i in 0..4 {
+        tx.send(i).unwrap();
+    }
+    std::thread::sleep(Duration::from_millis(20));
+    // the first recv swaps the rest into the local buffer, the stamps have to come along.
+    assert_eq!(rx.recv(), Ok(0));
+    let mut rx2 = rx.clone();
+    assert_eq!(rx.recv(), Ok(1));
+    // dropping hands the local buffer back, stamps included.
+    drop(rx);
+    let mut buf = Vec::new();
+    assert_eq!(rx2.recv_many(&mut buf, 10), Ok(2));
+    assert_eq!(buf, [2, 3]);
+
+    let hist = tx.latency().unwrap();
+    assert_eq!(hist.count(), 4);
+    assert!(hist.p50() >= Duration::from_millis(15));
+    assert!(hist.p99() <= hist.max());
+    assert_eq!(crate::unbounded::<()>().1.latency(), None);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn latency_of_rendezvous_hand_off() {
+    let (tx, mut rx) = crate::Builder::new().bounded(0).latency().build();
+    let handle = std::thread::spawn(move || {
+        tx.send(1).unwrap();
+        tx
+    });
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(rx.recv(), Ok(1));
+    handle.join().unwrap();
+
+    let hist = rx.latency().unwrap();
+    assert_eq!(hist.count(), 1);
+    assert!(hist.max() >= Duration::from_millis(15));
+}
+
+#[test]
+#[cfg(feature = "std")]
+#[should_panic(expected = "latency tracking isn't supported on lock-free channels")]
+fn latency_rejects_lock_free() {
+    crate::Builder::new().lock_free().latency().build::<()>();
+}
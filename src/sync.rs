@@ -0,0 +1,71 @@
+// the locking and waiting primitives behind the channels. With the `std` feature these are std's
+// own, without it spin-based stand-ins from `spin`. The rest of the crate only locks through
+// `lock`, `read` and `write` and parks through `wait`, so it doesn't care which ones it gets.
+
+#[cfg(not(feature = "std"))]
+mod spin;
+
+#[cfg(not(feature = "std"))]
+pub(crate) use spin::{
+    lock, read, timed_out, wait, write, Condvar, Instant, Mutex, MutexGuard, RwLock,
+    RwLockReadGuard, RwLockWriteGuard,
+};
+
+#[cfg(feature = "std")]
+pub(crate) use std::{
+    sync::{Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::Instant,
+};
+
+#[cfg(feature = "std")]
+use std::sync::PoisonError;
+
+// locks `mu` even if a thread panicked while holding it. Wakers run after unlocking and
+// notifying the condvars (see `Woken`), and messages a channel discards are dropped after
+// unlocking, so a panicking waker or `Drop` can't leave the state half updated or skip a
+// notification. The one waker run under the lock, by a rendezvous reciever about to park, comes
+// before any counter is bumped. So the other handles just carry on instead of spreading the panic,
+// and `Drop` never panics on a poisoned lock. The one exception is a panicking `Ord` of a priority
+// channel's messages, which (like in std's `BinaryHeap`) may leave the heap out of order, but
+// doesn't lose any message.
+#[cfg(feature = "std")]
+pub(crate) fn lock<S>(mu: &Mutex<S>) -> MutexGuard<'_, S> {
+    mu.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// like `lock`, the value behind an `RwLock` is only ever swapped out with `mem::replace`, which
+// can't panic halfway, so a poisoned one is still valid.
+#[cfg(feature = "std")]
+pub(crate) fn read<T>(rw: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    rw.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn write<T>(rw: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    rw.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+// parks on `cond` untill woken up or `deadline` is reached. The callers loop around this and
+// re-check their condition with the same absolute deadline, so spureous wake ups never extend the
+// total wait.
+#[cfg(feature = "std")]
+pub(crate) fn wait<'a, S>(
+    cond: &Condvar,
+    guard: MutexGuard<'a, S>,
+    deadline: Option<Instant>,
+) -> MutexGuard<'a, S> {
+    match deadline {
+        Some(deadline) => {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            cond.wait_timeout(guard, timeout)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0
+        }
+        None => cond.wait(guard).unwrap_or_else(PoisonError::into_inner),
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn timed_out(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
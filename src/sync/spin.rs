@@ -0,0 +1,229 @@
+// spin-based stand-ins for std's locks, for builds without the `std` feature. There is no OS to
+// park a thread with, so waiting means spinning on an atomic untill somebody else makes progress.
+// On a single core that only happens once the spinning thread gets preempted, the non-blocking
+// operations are the better fit there.
+
+use core::{
+    cell::UnsafeCell,
+    hint,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+pub(crate) struct Mutex<T> {
+    locked: AtomicBool,
+    val: UnsafeCell<T>,
+}
+
+// the lock hands out `&mut T` to one thread at a time, like `std::sync::Mutex`.
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub(crate) const fn new(val: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            val: UnsafeCell::new(val),
+        }
+    }
+}
+
+pub(crate) struct MutexGuard<'a, T> {
+    mu: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: we hold the lock.
+        unsafe { &*self.mu.val.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: we hold the lock.
+        unsafe { &mut *self.mu.val.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mu.locked.store(false, Ordering::Release);
+    }
+}
+
+// there is no poisoning, a panic just releases the lock while unwinding. That matches `lock` on
+// std, which recovers poisoned locks anyway.
+pub(crate) fn lock<T>(mu: &Mutex<T>) -> MutexGuard<'_, T> {
+    while mu
+        .locked
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        // only read while it's taken, so the cache line isn't bounced around by failed swaps.
+        while mu.locked.load(Ordering::Relaxed) {
+            hint::spin_loop();
+        }
+    }
+    MutexGuard { mu }
+}
+
+// `state` of an `RwLock` while it's write locked, otherwise it counts the readers.
+const WRITER: usize = usize::MAX;
+
+pub(crate) struct RwLock<T> {
+    state: AtomicUsize,
+    val: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    pub(crate) const fn new(val: T) -> Self {
+        Self {
+            state: AtomicUsize::new(0),
+            val: UnsafeCell::new(val),
+        }
+    }
+}
+
+pub(crate) struct RwLockReadGuard<'a, T> {
+    rw: &'a RwLock<T>,
+}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: we hold a read lock, nobody writes.
+        unsafe { &*self.rw.val.get() }
+    }
+}
+
+impl<T> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.rw.state.fetch_sub(1, Ordering::Release);
+    }
+}
+
+pub(crate) struct RwLockWriteGuard<'a, T> {
+    rw: &'a RwLock<T>,
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: we hold the write lock.
+        unsafe { &*self.rw.val.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: we hold the write lock.
+        unsafe { &mut *self.rw.val.get() }
+    }
+}
+
+impl<T> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.rw.state.store(0, Ordering::Release);
+    }
+}
+
+pub(crate) fn read<T>(rw: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    loop {
+        let state = rw.state.load(Ordering::Relaxed);
+        if state != WRITER
+            && rw
+                .state
+                .compare_exchange_weak(state, state + 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        {
+            return RwLockReadGuard { rw };
+        }
+        hint::spin_loop();
+    }
+}
+
+pub(crate) fn write<T>(rw: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    while rw
+        .state
+        .compare_exchange_weak(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        hint::spin_loop();
+    }
+    RwLockWriteGuard { rw }
+}
+
+// every notification bumps `seq`, a waiter spins untill it changes. Waking all waiters for a
+// `notify_one` is fine, they re-check their condition anyway.
+pub(crate) struct Condvar {
+    seq: AtomicUsize,
+}
+
+impl Condvar {
+    pub(crate) const fn new() -> Self {
+        Self {
+            seq: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+    }
+
+    pub(crate) fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// there is no clock without std, so no deadline can ever be set and `Option<Instant>` is always
+// `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Instant {}
+
+impl Instant {
+    pub(crate) fn elapsed(&self) -> Duration {
+        match *self {}
+    }
+}
+
+// spins untill `cond` gets notified. `seq` is read while still holding the lock, and notifiers
+// change the state before notifying, so a notification meant for us can't slip by.
+pub(crate) fn wait<'a, S>(
+    cond: &Condvar,
+    guard: MutexGuard<'a, S>,
+    deadline: Option<Instant>,
+) -> MutexGuard<'a, S> {
+    if let Some(deadline) = deadline {
+        match deadline {}
+    }
+
+    let seq = cond.seq.load(Ordering::Acquire);
+    let mu = guard.mu;
+    drop(guard);
+    while cond.seq.load(Ordering::Acquire) == seq {
+        hint::spin_loop();
+    }
+    lock(mu)
+}
+
+pub(crate) fn timed_out(deadline: Option<Instant>) -> bool {
+    match deadline {
+        Some(deadline) => match deadline {},
+        None => false,
+    }
+}
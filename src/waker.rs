@@ -0,0 +1,99 @@
+use alloc::vec::Vec;
+use core::task::Waker;
+#[cfg(feature = "std")]
+use std::panic::AssertUnwindSafe;
+
+// registry of wakers interested in a channel's state, used by async tasks and by `Select` to wait
+// on several channels at once. Entries stay registered untill their owner removes them, so a
+// waker may be woken more than once.
+pub(crate) struct Wakers {
+    entries: Vec<(usize, Waker)>,
+    next_id: usize,
+}
+
+impl Wakers {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    // registers `waker` under `*id`, or refreshes the entry `*id` already points to. Returns
+    // whether a new entry was added.
+    pub(crate) fn register(&mut self, id: &mut Option<usize>, waker: &Waker) -> bool {
+        if let Some(id) = *id {
+            if let Some((_, w)) = self.entries.iter_mut().find(|(i, _)| *i == id) {
+                if !w.will_wake(waker) {
+                    w.clone_from(waker);
+                }
+                return false;
+            }
+        }
+
+        let new = self.next_id;
+        self.next_id += 1;
+        self.entries.push((new, waker.clone()));
+        *id = Some(new);
+        true
+    }
+
+    // removes the entry `*id` points to and hands back its waker, so that the caller can update
+    // its own counters before the waker gets dropped.
+    pub(crate) fn unregister(&mut self, id: &mut Option<usize>) -> Option<Waker> {
+        let id = id.take()?;
+        let pos = self.entries.iter().position(|(i, _)| *i == id)?;
+        Some(self.entries.swap_remove(pos).1)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// wakers picked out of the registries while holding a channel's lock, to be woken once it is
+// released and the condvars are notified. A waker is user code and might panic, which then can't
+// skip a notification or leave the lock poisoned.
+#[derive(Default)]
+pub(crate) struct Woken(Vec<Waker>);
+
+impl Woken {
+    pub(crate) fn add(&mut self, wakers: &Wakers) {
+        self.0.extend(wakers.entries.iter().map(|(_, w)| w.clone()));
+    }
+
+    pub(crate) fn push(&mut self, waker: Waker) {
+        self.0.push(waker);
+    }
+
+    // wakes every waker, even past a panicking one. The first panic is carried on afterwards.
+    pub(crate) fn wake(self) {
+        #[cfg(feature = "std")]
+        {
+            let mut panic = None;
+            for waker in self.0 {
+                if let Err(p) = std::panic::catch_unwind(AssertUnwindSafe(|| waker.wake())) {
+                    panic.get_or_insert(p);
+                }
+            }
+            if let Some(p) = panic {
+                std::panic::resume_unwind(p);
+            }
+        }
+        #[cfg(not(feature = "std"))]
+        for waker in self.0 {
+            waker.wake();
+        }
+    }
+
+    // `wake` for `Drop` impls, which must not panic: a panicking waker is only reported by the
+    // panic hook. Without std there is no way to catch it.
+    pub(crate) fn wake_in_drop(self) {
+        #[cfg(feature = "std")]
+        for waker in self.0 {
+            let _ = std::panic::catch_unwind(AssertUnwindSafe(|| waker.wake()));
+        }
+        #[cfg(not(feature = "std"))]
+        self.wake();
+    }
+}
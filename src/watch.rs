@@ -0,0 +1,235 @@
+//! Watch channels, which only keep the latest value.
+//!
+//! The sender overwrites a single shared value instead of queueing messages, recievers read the
+//! current value whenever they like and can block untill a newer version gets sent. Intermediate
+//! versions a reciever didn't look at are simply skipped.
+
+use alloc::sync::Arc;
+use core::{mem, ops::Deref};
+
+use crate::{
+    lock,
+    sync::{read, write, Condvar, Mutex, RwLock, RwLockReadGuard},
+    wait, RecvError, SendErr,
+};
+
+struct Shared<T> {
+    value: RwLock<T>,
+    mu: Mutex<State>,
+    // recievers waiting in `changed` park here.
+    cond: Condvar,
+}
+
+struct State {
+    // bumped on every send while holding the write lock on `value`, so a reader holding the read
+    // lock sees the version matching the value.
+    version: u64,
+    senders: usize,
+    receivers: usize,
+}
+
+/// A borrowed reference to the value of a watch channel.
+///
+/// Holds a read lock, so keep it short: the sender blocks untill it is dropped.
+pub struct Ref<'a, T> {
+    guard: RwLockReadGuard<'a, T>,
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+/// The sending half of a watch channel, can be cloned to update from multiple threads.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Replaces the value and wakes every reciever blocked in [`changed`](Reciever::changed).
+    ///
+    /// Fails with [`SendErr`] holding the value if all recievers are gone.
+    pub fn send(&self, val: T) -> Result<(), SendErr<T>> {
+        let mut value = write(&self.shared.value);
+        let mut guard = lock(&self.shared.mu);
+        if guard.receivers == 0 {
+            return Err(SendErr(val));
+        }
+
+        // keep the previous value around untill both locks are released, so that no user code
+        // runs under them.
+        let old = mem::replace(&mut *value, val);
+        guard.version += 1;
+        drop(guard);
+        drop(value);
+        self.shared.cond.notify_all();
+        drop(old);
+        Ok(())
+    }
+
+    /// Borrows the current value.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            guard: read(&self.shared.value),
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        lock(&self.shared.mu).senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut guard = lock(&self.shared.mu);
+        guard.senders -= 1;
+        if guard.senders == 0 {
+            drop(guard);
+            self.shared.cond.notify_all();
+        }
+    }
+}
+
+/// The recieving half of a watch channel. Clones start out having seen the same version as the
+/// original.
+pub struct Reciever<T> {
+    shared: Arc<Shared<T>>,
+    // version of the value this reciever last looked at.
+    seen: u64,
+}
+
+impl<T> Reciever<T> {
+    /// Borrows the current value without marking it as seen.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            guard: read(&self.shared.value),
+        }
+    }
+
+    /// Borrows the current value and marks it as seen, a following [`changed`](Self::changed)
+    /// waits for a newer version.
+    pub fn borrow_and_update(&mut self) -> Ref<'_, T> {
+        let guard = read(&self.shared.value);
+        self.seen = lock(&self.shared.mu).version;
+        Ref { guard }
+    }
+
+    /// Whether a version newer than the last seen one was sent.
+    ///
+    /// Fails with [`RecvError`] once all senders are gone and there is no new version.
+    pub fn has_changed(&self) -> Result<bool, RecvError> {
+        let guard = lock(&self.shared.mu);
+        if guard.version != self.seen {
+            return Ok(true);
+        }
+        if guard.senders == 0 {
+            return Err(RecvError);
+        }
+        Ok(false)
+    }
+
+    /// Blocks untill a version newer than the last seen one was sent and marks it as seen, read
+    /// it with [`borrow`](Self::borrow).
+    ///
+    /// Fails with [`RecvError`] once all senders are gone and there is no new version.
+    pub fn changed(&mut self) -> Result<(), RecvError> {
+        let mut guard = lock(&self.shared.mu);
+        loop {
+            if guard.version != self.seen {
+                self.seen = guard.version;
+                return Ok(());
+            }
+            if guard.senders == 0 {
+                return Err(RecvError);
+            }
+            guard = wait(&self.shared.cond, guard, None);
+        }
+    }
+}
+
+impl<T> Clone for Reciever<T> {
+    fn clone(&self) -> Self {
+        lock(&self.shared.mu).receivers += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<T> Drop for Reciever<T> {
+    fn drop(&mut self) {
+        lock(&self.shared.mu).receivers -= 1;
+    }
+}
+
+/// Creates a watch channel holding `initial`, which counts as already seen by the reciever.
+pub fn watch<T>(initial: T) -> (Sender<T>, Reciever<T>) {
+    let shared = Arc::new(Shared {
+        value: RwLock::new(initial),
+        mu: Mutex::new(State {
+            version: 0,
+            senders: 1,
+            receivers: 1,
+        }),
+        cond: Condvar::new(),
+    });
+    (
+        Sender {
+            shared: Arc::clone(&shared),
+        },
+        Reciever { shared, seen: 0 },
+    )
+}
+
+#[test]
+fn changed_skips_stale_versions() {
+    let (tx, mut rx) = watch(0);
+    assert_eq!(*rx.borrow(), 0);
+    assert_eq!(rx.has_changed(), Ok(false));
+    for i in 1..=3 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(rx.has_changed(), Ok(true));
+    assert_eq!(rx.changed(), Ok(()));
+    assert_eq!(*rx.borrow(), 3);
+    assert_eq!(rx.has_changed(), Ok(false));
+}
+
+#[test]
+fn changed_blocks_untill_send() {
+    let (tx, mut rx) = watch("v1");
+    let handle = std::thread::spawn(move || {
+        rx.changed().unwrap();
+        let v = *rx.borrow();
+        // the sender is gone after its last update.
+        (v, rx.changed())
+    });
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    tx.send("v2").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    drop(tx);
+    assert_eq!(handle.join().unwrap(), ("v2", Err(RecvError)));
+}
+
+#[test]
+fn borrow_and_update_marks_seen() {
+    let (tx, mut rx1) = watch(1);
+    let rx2 = rx1.clone();
+    tx.send(2).unwrap();
+    assert_eq!(*rx1.borrow_and_update(), 2);
+    assert_eq!(rx1.has_changed(), Ok(false));
+    assert_eq!(rx2.has_changed(), Ok(true));
+    drop(rx1);
+    drop(rx2);
+    assert_eq!(tx.send(3), Err(SendErr(3)));
+    assert_eq!(*tx.borrow(), 2);
+}